@@ -26,6 +26,17 @@ const Input = styled.input<{ styles?: (props: any) => any, style: any }>`
 `;
 
 
+const Select = styled.select<{ styles?: (props: any) => any }>`
+    width: 100%;
+    border-radius: 30px;
+    background-color: white;
+    padding: 2px 6px;
+    border: 1px solid #999;
+    font-size: 110%;
+    box-sizing: border-box;
+    ${props => props.styles && props.styles(props)}
+`;
+
 export function Control<N extends ControlType>(props: { data: InputControl<N>, styles?: () => any }) {
     const properties = props.data.options.properties;
 
@@ -59,6 +70,26 @@ export function Control<N extends ControlType>(props: { data: InputControl<N>, s
     }, [props.data.value])
 
 
+    if (props.data.type === 'select') {
+        return (
+            <Label title={props.data.tooltip} type={props.data.type} ref={ref}>
+                {props.data.label}
+                <Select
+                    value={value as string}
+                    disabled={props.data.readonly}
+                    onChange={(e: React.ChangeEvent<HTMLSelectElement>) => {
+                        const val = e.target.value as ControlTypeValue<N>;
+                        setValue(val)
+                        props.data.setValue(val)
+                    }}
+                    styles={props.styles}
+                >
+                    {(props.data.choices ?? []).map(c => <option key={c} value={c}>{c}</option>)}
+                </Select>
+            </Label>
+        )
+    }
+
     return (
         <Label title={props.data.tooltip} type={props.data.type} ref={ref}>
             {props.data.label}
@@ -2,7 +2,7 @@ import { removeTab } from '../codeEditor/index';
 import { openModal } from '../modal';
 import { processedQueries } from '../processed-queries';
 
-import { saveGraph, saveEvents } from './save';
+import { saveGraph, saveEvents, serializeGraph } from './save';
 import { Node, geojsonNodeList, queryNodeList, NodeList } from './nodes';
 import { Control as ControlComponent } from './Control';
 import { StyledNode } from './Node';
@@ -120,13 +120,26 @@ const contextMenu = new ContextMenuPlugin<Schemes>({
                                         await editor.removeNode(n.id);
                                     }
                                 }
-                            }
+                            },
+                            {
+                                label: 'Download graph',
+                                key: '2',
+                                handler: () => {
+                                    const blob = new Blob([JSON.stringify(serializeGraph())], { type: 'application/json' });
+                                    const a = document.createElement('a');
+                                    a.href = URL.createObjectURL(blob);
+                                    a.download = 'graph.json';
+                                    a.click();
+                                    URL.revokeObjectURL(a.href);
+                                }
+                            },
                         ]
                     },
                 ],
             };
         } else if ("label" in context) {
-            if (context.label === 'Map') {
+            // there always has to be at least one map
+            if (context.label === 'Map' && editor.getNodes().filter(n => n.label === 'Map').length <= 1) {
                 return {
                     searchBar: false,
                     list: []
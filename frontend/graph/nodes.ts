@@ -15,6 +15,8 @@ export type InputControlOptions<N> = {
     /** Callback function that is called when the control value changes */
     change?: (value: N) => void
     properties?: ExtraProperties<N>;
+    /** Values to choose from, for `select` controls */
+    choices?: string[];
 
     label?: string;
     tooltip?: string;
@@ -32,6 +34,7 @@ type ControlTypeMap = {
     text: string;
     number: number;
     checkbox: boolean;
+    select: string;
 }
 export type ControlType = keyof ControlTypeMap;
 export type ControlTypeValue<T extends ControlType> = ControlTypeMap[T];
@@ -44,6 +47,7 @@ export class Control<T extends ControlType, N = ControlTypeValue<T>> extends Cla
     properties: ExtraProperties<N>;
     label?: string;
     tooltip?: string;
+    choices?: string[];
     value?: N;
     readonly: boolean;
 
@@ -54,6 +58,7 @@ export class Control<T extends ControlType, N = ControlTypeValue<T>> extends Cla
         this.properties = options.properties;
         this.label = options?.label;
         this.tooltip = options?.tooltip;
+        this.choices = options?.choices;
     }
 
     setValue(value?: N) {
@@ -69,9 +74,27 @@ export type NodeList = [key: string, factory: () => Node][];
 export const geojsonNodeList: NodeList = [
     ["Overpass", oqlNode],
     ["Union", union],
+    ["Difference", difference],
+    ["Intersection", intersection],
     ["Road Angle Filter", roadAngleFilter],
     ["Road Length Filter", roadLengthFilter],
+    ["Area Filter", areaFilter],
+    ["Junctions", junctions],
+    ["Merge Ways", mergeWays],
     ["Elevation Filter", elevationFilter],
+    ["In View Of", inViewOf],
+    ["Slope Filter", slopeFilter],
+    ["Elevation Profile", elevationProfile],
+    ["Tag Filter", tagFilter],
+    ["Within Distance", withinDistance],
+    ["Intersects", intersects],
+    ["Inside", inside],
+    ["Cluster", cluster],
+    ["Buffer", buffer],
+    ["Centroid", centroid],
+    ["Hull", hull],
+    ["Simplify", simplify],
+    ["Map", map],
 ];
 
 export const queryNodeList: NodeList = [
@@ -159,6 +182,92 @@ export function roadLengthFilter(): Node {
     return node;
 }
 
+export function areaFilter(): Node {
+    const node = new ClassicPreset.Node("Area Filter") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("measure", new Control("select", {
+        initial: 'area',
+        label: 'measure',
+        tooltip: 'area in m², perimeter in m, or compactness from 0 for a line to 1 for a circle',
+        choices: ['area', 'perimeter', 'compactness'],
+    }));
+    node.addControl("min", new Control("number", {
+        initial: 20000,
+        label: 'min',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("max", new Control("number", {
+        initial: 1000000,
+        label: 'max',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("write_properties", new Control("checkbox", {
+        initial: false,
+        label: 'write properties',
+        tooltip: 'add area, perimeter and compactness to the properties of the features',
+    }));
+    return node;
+}
+
+export function junctions(): Node {
+    const node = new ClassicPreset.Node("Junctions") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("min_degree", new Control("number", {
+        initial: 3,
+        label: 'min degree',
+        tooltip: 'amount of road segments leaving the junction, a road passing through it counts twice',
+        properties: {
+            min: 2,
+        }
+    }));
+    node.addControl("max_degree", new Control("number", {
+        initial: 8,
+        label: 'max degree',
+        properties: {
+            min: 2,
+        }
+    }));
+    node.addControl("min_angle", new Control("number", {
+        initial: 0,
+        label: 'min angle',
+        tooltip: 'bounds for the smallest angle between two neighbouring road segments, in degrees',
+        properties: {
+            min: 0,
+            max: 360,
+        }
+    }));
+    node.addControl("max_angle", new Control("number", {
+        initial: 180,
+        label: 'max angle',
+        properties: {
+            min: 0,
+            max: 360,
+        }
+    }));
+    return node;
+}
+
+export function mergeWays(): Node {
+    const node = new ClassicPreset.Node("Merge Ways") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("keys", new Control("text", {
+        initial: 'name,highway',
+        label: 'keys',
+        tooltip: 'comma separated tags that connected roads need to share to be merged',
+    }));
+    return node;
+}
+
 export function elevationFilter(): Node {
     const node = new ClassicPreset.Node("Elevation Filter") as Node;
     node.type = "geojson";
@@ -172,6 +281,242 @@ export function elevationFilter(): Node {
         initial: 35,
         label: 'max',
     }));
+    node.addControl("missing", new Control("select", {
+        initial: 'drop',
+        label: 'no data',
+        tooltip: 'what to do with features without elevation data. flag keeps them, setting elevation_missing',
+        choices: ['drop', 'keep', 'flag'],
+    }));
+    node.addControl("interpolation", new Control("select", {
+        initial: 'bilinear',
+        label: 'interpolation',
+        tooltip: 'how to calculate the elevation between the pixels of the elevation data',
+        choices: ['nearest', 'bilinear', 'cubic'],
+    }));
+    return node;
+}
+
+export function slopeFilter(): Node {
+    const node = new ClassicPreset.Node("Slope Filter") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("min", new Control("number", {
+        initial: 10,
+        label: 'min',
+        tooltip: 'minimum grade of a segment, in percent',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("max", new Control("number", {
+        initial: 100,
+        label: 'max',
+        tooltip: 'maximum grade of a segment, in percent',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("merge", new Control("checkbox", {
+        initial: true,
+        label: 'merge',
+        tooltip: 'join consecutive matching segments into a single line',
+    }));
+    return node;
+}
+
+export function elevationProfile(): Node {
+    const node = new ClassicPreset.Node("Elevation Profile") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("three_d", new Control("checkbox", {
+        initial: false,
+        label: '3D coordinates',
+        tooltip: 'add the elevation as the third value of every coordinate',
+    }));
+    return node;
+}
+
+export function tagFilter(): Node {
+    const node = new ClassicPreset.Node("Tag Filter") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Matching"));
+    node.addOutput("rejected", new ClassicPreset.Output(geojsonSocket, "Not matching"));
+    node.addControl("filter", new Control("text", {
+        initial: '[highway]',
+        label: 'filter',
+        tooltip: 'conditions features have to match, like [highway=residential][!name][name~"^av",i][maxspeed>30]',
+    }));
+    return node;
+}
+
+export function withinDistance(): Node {
+    const node = new ClassicPreset.Node("Within Distance") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addInput("aux", new ClassicPreset.Input(geojsonSocket, "Near"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Within distance"));
+    node.addOutput("rejected", new ClassicPreset.Output(geojsonSocket, "Further away"));
+    node.addControl("distance", new Control("number", {
+        initial: 50,
+        label: 'distance',
+        tooltip: 'max distance to any of the features of the second input, in meters',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    return node;
+}
+
+export function intersects(): Node {
+    const node = new ClassicPreset.Node("Intersects") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addInput("aux", new ClassicPreset.Input(geojsonSocket, "Intersecting"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Intersecting"));
+    node.addOutput("rejected", new ClassicPreset.Output(geojsonSocket, "Not intersecting"));
+    return node;
+}
+
+export function inside(): Node {
+    const node = new ClassicPreset.Node("Inside") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addInput("aux", new ClassicPreset.Input(geojsonSocket, "Areas"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Inside"));
+    node.addOutput("rejected", new ClassicPreset.Output(geojsonSocket, "Outside"));
+    return node;
+}
+
+export function cluster(): Node {
+    const node = new ClassicPreset.Node("Cluster") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Clusters"));
+    node.addOutput("rejected", new ClassicPreset.Output(geojsonSocket, "Not clustered"));
+    node.addControl("algorithm", new Control("select", {
+        initial: 'dbscan',
+        label: 'algorithm',
+        tooltip: 'dbscan groups features with enough neighbours within the distance, grid groups features in the same cell',
+        choices: ['dbscan', 'grid'],
+    }));
+    node.addControl("distance", new Control("number", {
+        initial: 30,
+        label: 'distance',
+        tooltip: 'in meters. max distance between neighbours for dbscan, cell size for grid',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("min_points", new Control("number", {
+        initial: 3,
+        label: 'min points',
+        tooltip: 'minimum amount of features in a cluster',
+        properties: {
+            min: 1,
+            step: 1,
+        }
+    }));
+    node.addControl("output", new Control("select", {
+        initial: 'centroids',
+        label: 'output',
+        tooltip: 'a point or hull per cluster with member_count and member_ids, or the clustered features with their cluster_id',
+        choices: ['centroids', 'hulls', 'points'],
+    }));
+    return node;
+}
+
+export function buffer(): Node {
+    const node = new ClassicPreset.Node("Buffer") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("distance", new Control("number", {
+        initial: 50,
+        label: 'distance',
+        tooltip: 'in meters',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    return node;
+}
+
+export function centroid(): Node {
+    const node = new ClassicPreset.Node("Centroid") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    return node;
+}
+
+export function hull(): Node {
+    const node = new ClassicPreset.Node("Hull") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("kind", new Control("select", {
+        initial: 'convex',
+        label: 'kind',
+        choices: ['convex', 'concave'],
+    }));
+    node.addControl("concavity", new Control("number", {
+        initial: 2.0,
+        label: 'concavity',
+        tooltip: 'only used by concave hulls, lower values follow the features more closely',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("per_feature", new Control("checkbox", {
+        initial: false,
+        label: 'per feature',
+        tooltip: 'get the hull of every feature, instead of a single hull for all of them',
+    }));
+    return node;
+}
+
+export function simplify(): Node {
+    const node = new ClassicPreset.Node("Simplify") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Out"));
+    node.addControl("tolerance", new Control("number", {
+        initial: 5,
+        label: 'tolerance',
+        tooltip: 'in meters, points closer than this to the simplified line are removed',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    return node;
+}
+
+export function inViewOf(): Node {
+    const node = new ClassicPreset.Node("In View Of") as Node;
+    node.type = "geojson";
+    node.addInput("in", new ClassicPreset.Input(geojsonSocket, "In"));
+    node.addInput("aux", new ClassicPreset.Input(geojsonSocket, "Visible from"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "Visible"));
+    node.addOutput("rejected", new ClassicPreset.Output(geojsonSocket, "Not visible"));
+    node.addControl("height", new Control("number", {
+        initial: 1.7,
+        label: 'height',
+        tooltip: 'height of the observer above the ground, in meters',
+        properties: {
+            min: 0.0,
+        }
+    }));
+    node.addControl("max_distance", new Control("number", {
+        initial: 5000,
+        label: 'max distance',
+        tooltip: 'maximum distance between features, in meters',
+        properties: {
+            min: 0.0,
+        }
+    }));
     return node;
 }
 
@@ -180,6 +525,19 @@ export function map(): Node {
     const node = new ClassicPreset.Node("Map") as Node;
     node.type = "geojson";
     node.addInput("in", new ClassicPreset.Input(geojsonSocket, "GeoJson"));
+
+    const layerCount = editor.getNodes().filter(n => n.label == "Map").length + 1;
+
+    node.addControl("name", new Control("text", {
+        initial: `Layer ${layerCount}`,
+        label: 'name',
+        tooltip: 'name of the layer the results are shown as',
+    }));
+    node.addControl("color", new Control("text", {
+        initial: '',
+        label: 'color',
+        tooltip: 'css color to display the layer with, leave empty for the default colors',
+    }));
     return node;
 }
 
@@ -192,6 +550,35 @@ export function union(): Node {
     return node;
 }
 
+export function difference(): Node {
+    const node = new ClassicPreset.Node("Difference") as Node;
+    node.type = "geojson";
+    node.addInput("a", new ClassicPreset.Input(geojsonSocket, "A"));
+    node.addInput("b", new ClassicPreset.Input(geojsonSocket, "B"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "A - B"));
+    node.addControl("compare", compareControl());
+    return node;
+}
+
+export function intersection(): Node {
+    const node = new ClassicPreset.Node("Intersection") as Node;
+    node.type = "geojson";
+    node.addInput("a", new ClassicPreset.Input(geojsonSocket, "A"));
+    node.addInput("b", new ClassicPreset.Input(geojsonSocket, "B"));
+    node.addOutput("out", new ClassicPreset.Output(geojsonSocket, "A ∩ B"));
+    node.addControl("compare", compareControl());
+    return node;
+}
+
+function compareControl() {
+    return new Control("select", {
+        initial: 'osm',
+        label: 'compare',
+        tooltip: 'whether features are the same when they have the same osm id, or the same geometry',
+        choices: ['osm', 'geometry'],
+    });
+}
+
 export function oqlCode(selected: boolean): Node {
     const node = new ClassicPreset.Node('OQL Code') as Node;
     node.type = 'query';
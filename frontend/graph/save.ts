@@ -15,10 +15,11 @@ type SerializedNode = {
     controls: {
         [key in string]?: {
             id: string;
-            type: 'text' | 'number';
-            value: string | number;
+            type: 'text' | 'number' | 'checkbox' | 'select';
+            value: string | number | boolean;
             readonly: boolean;
             properties: ExtraProperties<string | number>;
+            choices?: string[];
             label: string;
             tooltip: string;
         }
@@ -153,6 +154,7 @@ async function loadGraph() {
                         properties: control.properties,
                         label: control.label,
                         tooltip: control.tooltip,
+                        choices: control.choices,
                     });
                     node.addControl(key, ctrl);
                 }
@@ -13,14 +13,42 @@ document.querySelector<HTMLButtonElement>('#clear-button').onclick = () => {
     setMapData('OverpassAPI', { type: "FeatureCollection", features: [] });
 };
 
-document.querySelector<HTMLButtonElement>('#export-button').onclick = () => {
+document.querySelector<HTMLButtonElement>('#export-button').onclick = async () => {
     const out = getMapData('OverpassAPI');
     if (out.data.features.length == 0) {
         alert('No data to export!');
-    } else {
+        return;
+    }
+
+    const format = document.querySelector<HTMLSelectElement>('#export-format').value;
+    if (format === 'geojson') {
         downloadAsJsonFile('export.json', out);
+        return;
     }
+
+    // the other formats are converted by the server
+    const r = await fetch(`/export?format=${format}`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(out.data),
+    });
+    if (!r.ok) {
+        alert(await r.text());
+        return;
+    }
+    downloadBlob(`export.${format}`, await r.blob());
 };
+
+/**
+ * Download the provided blob as a file
+ */
+function downloadBlob(filename: string, blob: Blob) {
+    const element = document.createElement('a');
+    element.href = URL.createObjectURL(blob);
+    element.download = filename;
+    element.click();
+    URL.revokeObjectURL(element.href);
+}
 /**
  * Download the provided object as a JSON file
  */
@@ -1,6 +1,8 @@
 // loading modal doesnt use openModal cause we want this custom style which looks nicer imo
 let loading = false;
 let loadingModal: HTMLDivElement | null = document.querySelector("#loading-modal");
+let loadingMessage: HTMLParagraphElement | null = document.querySelector("#loading-modal p");
+const defaultMessage = loadingMessage?.innerHTML;
 
 /**
  * Enables/disables the loading modal
@@ -8,6 +10,14 @@ let loadingModal: HTMLDivElement | null = document.querySelector("#loading-modal
 export function setLoading(value: boolean): void {
     loading = value;
     if (loadingModal) loadingModal.style.display = loading ? 'flex' : 'none';
+    if (loadingMessage) loadingMessage.innerHTML = defaultMessage;
+}
+
+/**
+ * Sets the message displayed on the loading modal
+ */
+export function setLoadingMessage(message: string): void {
+    if (loadingMessage) loadingMessage.innerText = message;
 }
 
 /**
@@ -94,10 +94,13 @@ map.on("style.load", () => {
             let point: LngLat;
             if (geometry.type == 'Point') {
                 point = new LngLat(geometry.coordinates[0], geometry.coordinates[1]);
-            } else if (geometry.type == 'LineString' || geometry.type == 'Polygon') {
+            } else if (geometry.type == 'LineString' || geometry.type == 'Polygon' || geometry.type == 'MultiPolygon') {
+                const coordinates: any[] = geometry.type == 'LineString' ? geometry.coordinates
+                    : geometry.type == 'Polygon' ? geometry.coordinates.flat()
+                        : geometry.coordinates.flat(2);
                 const nearest = turf.nearestPoint(
                     turf.point([e.lngLat.lng, e.lngLat.lat]),
-                    turf.featureCollection(geometry.coordinates.map((p: any) => turf.point(p)))
+                    turf.featureCollection(coordinates.map((p: any) => turf.point(p)))
                 );
                 point = new LngLat(nearest.geometry.coordinates[0], nearest.geometry.coordinates[1]);
             } else {
@@ -117,7 +120,17 @@ map.on("style.load", () => {
             ${props}<br/><br/>
             <a href="https://google.co.uk/maps?q=${point.lat},${point.lng}" target="_blank" class="map-link google-maps-link">google maps</a>
             <br/>
-            <a href="javascript:navigator.clipboard.writeText('${point.lat},${point.lng}')" class="map-link">copy</a>`;
+            <a href="javascript:navigator.clipboard.writeText('${point.lat},${point.lng}')" class="map-link">copy</a>
+            <span class="elevation"></span>`;
+
+            fetch(`/elevation?lng=${point.lng}&lat=${point.lat}`)
+                .then(res => res.json())
+                .then(res => {
+                    if (res.elevation != null) {
+                        div.querySelector('.elevation').innerHTML = `<br/>elevation: ${res.elevation} m`;
+                    }
+                })
+                .catch(() => { });
 
             div.querySelector<HTMLAnchorElement>('.google-maps-link').onclick = () => {
                 markAsVisited(f, true);
@@ -186,17 +199,21 @@ map.on("style.load", () => {
                 id: "overpass-polygons",
                 type: "fill",
                 source: "OverpassAPI",
-                filter: ["all", ["==", ["geometry-type"], "Polygon"]],
+                filter: ["in", ["geometry-type"], ["literal", ["Polygon", "MultiPolygon"]]],
                 paint: {
-                    "fill-color": "rgba(255, 204, 0, .5)",
+                    "fill-color": ["coalesce", ["get", "__color"], "rgba(255, 204, 0, 1)"],
+                    "fill-opacity": 0.5,
                 },
             },
             {
                 id: "overpass-polygons-stroke",
                 type: "line",
                 source: "OverpassAPI",
-                filter: ["all", ["==", ["geometry-type"], "Polygon"]],
-                paint: { "line-width": 2, "line-color": "rgba(0, 51, 255, 0.6)" },
+                filter: ["in", ["geometry-type"], ["literal", ["Polygon", "MultiPolygon"]]],
+                paint: {
+                    "line-width": 2,
+                    "line-color": ["coalesce", ["get", "__color"], "rgba(0, 51, 255, 0.6)"],
+                },
             },
             {
                 id: "overpass-lines",
@@ -209,7 +226,7 @@ map.on("style.load", () => {
                         'case',
                         ['boolean', ['feature-state', 'selected'], false], "rgba(200, 51, 255, 0.6)",
                         ['boolean', ['feature-state', 'visited'], false], "rgba(0, 204, 200, 0.6)",
-                        "rgba(0, 51, 255, 0.6)",
+                        ["coalesce", ["get", "__color"], "rgba(0, 51, 255, 0.6)"],
                     ]
 
                 },
@@ -226,7 +243,7 @@ map.on("style.load", () => {
                     "circle-color": [
                         'case',
                         ['boolean', ['feature-state', 'visited'], false], "rgba(0, 204, 200, 0.6)",
-                        "rgba(250, 204, 0, 0.6)",
+                        ["coalesce", ["get", "__color"], "rgba(250, 204, 0, 0.6)"],
                     ]
                 },
             },
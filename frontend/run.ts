@@ -1,10 +1,11 @@
 import { Feature } from 'geojson';
 
 import { processedQueries } from './processed-queries';
-import { SearchError, SearchSuccess, search } from './search';
-import { setLoading, isLoading } from './loading';
+import { NodeEvent, SearchError, SearchSuccess, searchStream } from './search';
+import { setLoading, isLoading, setLoadingMessage } from './loading';
 import { mapBounds, setMapData } from './map';
 import { serializeGraph } from './graph/save';
+import { editor } from './graph/index';
 import { settings } from './settings';
 
 let resultsDiv: HTMLDivElement = document.querySelector("#results") as HTMLDivElement;
@@ -16,7 +17,7 @@ async function run() {
     setMapData('OverpassAPI', { type: "FeatureCollection", features: [] });
 
     try {
-        const response = await search(mapBounds(), serializeGraph());
+        const response = await searchStream(mapBounds(), serializeGraph(), handleNodeEvent);
 
         resultsDiv.innerHTML = '';
         if (response.ok === 'true') {
@@ -34,19 +35,52 @@ async function run() {
     setLoading(false);
 }
 
+function handleNodeEvent(event: NodeEvent) {
+    const label = editor.getNode(event.node_id)?.label ?? event.node_id;
+
+    if (event.type === 'started') {
+        setLoadingMessage(`running ${label}...`);
+    } else if (event.type === 'cache_hit') {
+        setLoadingMessage(`${label}: using cached results`);
+    } else if (event.type === 'finished') {
+        const count = event.feature_count === null ? '' : ` (${event.feature_count} features)`;
+        setLoadingMessage(`finished ${label}${count}`);
+    } else if (event.type === 'error') {
+        setLoadingMessage(`${label} failed: ${event.error}`);
+    }
+}
+
 function handleRunSuccess(response: SearchSuccess) {
-    const data = response.data;
+    // all layers are shown in the same source, tagging each feature with its layer
+    const features: Feature[] = [];
+    for (const layer of response.layers) {
+        let layerFeatures: Feature[] = layer.data.features;
+
+        if (settings.hideEmptyNodes()) {
+            layerFeatures = layerFeatures
+                .filter((f: Feature) => !(f.geometry.type === "Point" && Object.keys(f.properties).length == 0));
+        }
 
-    if (settings.hideEmptyNodes()) {
-        data.features = data.features
-            .filter((f: Feature) => !(f.geometry.type === "Point" && Object.keys(f.properties).length == 0));
+        for (const f of layerFeatures) {
+            f.properties = { ...f.properties, __layer: layer.name };
+            if (layer.color) f.properties.__color = layer.color;
+            features.push(f);
+        }
     }
 
-    setMapData('OverpassAPI', data);
+    setMapData('OverpassAPI', { type: "FeatureCollection", features });
+
+    if (response.layers.length > 1) {
+        const layers = response.layers.map((l) => {
+            const color = l.color ? `<span class="layer-color" style="background: ${l.color}"></span>` : '';
+            return `${color}${l.name} - ${l.data.features.length} features<br/>`;
+        }).join('');
+        resultsDiv.innerHTML += `<h2>Layers:</h2>${layers}`;
+    }
 
     if (response.geocode_areas.length > 0) {
         const areas = response.geocode_areas.map((a: any) => `${a.original} - <a href="//www.openstreetmap.org/${a.ty}/${a.id}" target="_blank" class="osm-link">${a.name}</a><br/>`).join('');
-        resultsDiv.innerHTML = `<h2>Geocode areas found:</h2>${areas}`;
+        resultsDiv.innerHTML += `<h2>Geocode areas found:</h2>${areas}`;
     }
 
     if (response.processed_queries) {
@@ -4,14 +4,26 @@ import { serializeGraph } from './graph/save';
 export type SearchGraph = ReturnType<typeof serializeGraph>;
 export type SearchResult = SearchSuccess | SearchError;
 
+export type Layer = {
+    name: string,
+    /// CSS color to display the layer with
+    color: string | null,
+    data: any,
+};
+
 export type SearchSuccess = {
     ok: 'true',
-    data: any,
+    /// One layer for every Map node
+    layers: Layer[],
     processed_queries: {
         /// Node Id -> Processed query
         [nodeId: string]: string,
     },
-    geocode_areas: any[]
+    geocode_areas: any[],
+    endpoints: {
+        /// Node Id -> Overpass endpoint used
+        [nodeId: string]: string,
+    },
 };
 export type SearchError = {
     ok: 'false',
@@ -28,6 +40,74 @@ export type SearchError = {
     }
 };
 
+export type NodeEvent = {
+    type: 'started' | 'cache_hit',
+    node_id: string,
+} | {
+    type: 'finished',
+    node_id: string,
+    /// Amount of features, if the node outputs geojson
+    feature_count: number | null,
+} | {
+    type: 'error',
+    node_id: string,
+    error: string,
+};
+
+/**
+ * Same as `search`, but calls `onEvent` as every node is processed
+ */
+export async function searchStream(
+    mapBounds: MapBounds,
+    graph: SearchGraph,
+    onEvent: (event: NodeEvent) => void
+): Promise<SearchResult> {
+    const r = await fetch('/search/stream', {
+        method: 'POST',
+        body: JSON.stringify({
+            bbox: mapBounds,
+            graph,
+        }),
+        headers: {
+            'Content-Type': 'application/json'
+        },
+    });
+
+    const reader = r.body.pipeThrough(new TextDecoderStream()).getReader();
+
+    // server sent events are separated by an empty line
+    let buffer = '';
+    while (true) {
+        const { value, done } = await reader.read();
+        if (done) break;
+        buffer += value;
+
+        let end: number;
+        while ((end = buffer.indexOf('\n\n')) !== -1) {
+            const message = buffer.slice(0, end);
+            buffer = buffer.slice(end + 2);
+
+            let event = 'message';
+            let data = '';
+            for (const line of message.split('\n')) {
+                if (line.startsWith('event:')) event = line.slice(6).trim();
+                else if (line.startsWith('data:')) data += line.slice(5).trim();
+            }
+            if (!data) continue;
+
+            if (event === 'node') {
+                onEvent(JSON.parse(data));
+            } else if (event === 'result') {
+                return { ok: 'true', ...JSON.parse(data) };
+            } else if (event === 'error') {
+                return { ok: 'false', ...JSON.parse(data) };
+            }
+        }
+    }
+
+    throw new TypeError('search stream ended without a result');
+}
+
 export async function search(mapBounds: MapBounds, graph: SearchGraph): Promise<SearchResult> {
     const r = await fetch('/search', {
         method: 'POST',
@@ -15,7 +15,6 @@ fi
 mkdir -p $OUTPUTDIR
 
 $SCRIPTDIR/download-srtm-data.sh $OUTPUTDIR
-$SCRIPTDIR/create-tiles.sh $OUTPUTDIR/SRTM_NE_250m.tif 10 10
-$SCRIPTDIR/create-tiles.sh $OUTPUTDIR/SRTM_SE_250m.tif 10 10
-$SCRIPTDIR/create-tiles.sh $OUTPUTDIR/SRTM_W_250m.tif 10 20
+cargo run --release --bin update-elevation -- --out $OUTPUTDIR \
+    $OUTPUTDIR/SRTM_NE_250m.tif $OUTPUTDIR/SRTM_SE_250m.tif $OUTPUTDIR/SRTM_W_250m.tif
 rm -rf $OUTPUTDIR/SRTM_NE_250m.tif $OUTPUTDIR/SRTM_SE_250m.tif $OUTPUTDIR/SRTM_W_250m.tif
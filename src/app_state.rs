@@ -1,19 +1,21 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
-use crate::{cache::Caches, elevation::ElevationMap};
+use crate::{cache::Caches, elevation::ElevationMap, overpass::OverpassClient};
 
 pub struct AppState {
-    pub elevation_map: ElevationMap,
+    pub elevation_map: Arc<ElevationMap>,
     pub data_path: PathBuf,
     pub caches: Caches,
+    pub overpass: OverpassClient,
 }
 
 impl AppState {
-    pub fn new(data_path: PathBuf, elevation_map: ElevationMap) -> Self {
+    pub fn new(data_path: PathBuf, elevation_map: ElevationMap, overpass: OverpassClient) -> Self {
         AppState {
-            elevation_map,
+            elevation_map: Arc::new(elevation_map),
+            caches: Caches::from_env(&data_path),
             data_path,
-            caches: Caches::new(),
+            overpass,
         }
     }
 }
@@ -0,0 +1,191 @@
+use underpass::{
+    cache::Caches,
+    elevation::{elevation_sources, ElevationMap, DEFAULT_CACHE_SIZE},
+    graph::{process::process_graph, Graph},
+    overpass::OverpassClient,
+    search::{Bbox, SearchError},
+};
+
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use thiserror::Error;
+
+/// Version of the graph format saved by the frontend that we know how to run
+const GRAPH_VERSION: &str = "2";
+
+const USAGE: &str = "usage: DATA_PATH=<data folder> underpass-run <graph.json> <south,west,north,east> <output folder>";
+
+#[tokio::main]
+async fn main() {
+    // we only care if the error is a line parse
+    if let Err(err @ dotenv::Error::LineParse(..)) = dotenv::dotenv() {
+        panic!("{:?}", err);
+    }
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let [graph_path, bbox, output_path] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+
+    let Some(bbox) = parse_bbox(bbox) else {
+        eprintln!("invalid bbox {bbox:?}");
+        eprintln!("{USAGE}");
+        std::process::exit(2);
+    };
+
+    if let Err(err) = run(graph_path.into(), bbox, output_path.into()).await {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(graph_path: PathBuf, bbox: Bbox, output_path: PathBuf) -> Result<(), RunError> {
+    let data_path: PathBuf = std::env::var_os("DATA_PATH")
+        .ok_or(RunError::DataPath)?
+        .into();
+
+    let saved: SavedGraph = serde_json::from_str(&std::fs::read_to_string(graph_path)?)?;
+    if saved.version != GRAPH_VERSION {
+        return Err(RunError::Version(saved.version));
+    }
+
+    let elevation_map =
+        ElevationMap::from_sources(&elevation_sources(&data_path), DEFAULT_CACHE_SIZE)?;
+    let overpass = OverpassClient::from_env();
+
+    println!("running graph");
+    let result = process_graph(
+        saved.graph,
+        bbox,
+        Arc::new(elevation_map),
+        Caches::from_env(&data_path),
+        &overpass,
+        None,
+    )
+    .await?;
+
+    std::fs::create_dir_all(&output_path)?;
+    let names = layer_file_names(result.layers.iter().map(|l| l.name.as_str()));
+    for (layer, name) in result.layers.iter().zip(names) {
+        println!(
+            "found {} features in layer {:?}, writing {name}",
+            layer.data.features.len(),
+            layer.name
+        );
+        write_json(&output_path, &name, &layer.data)?;
+    }
+    write_json(
+        &output_path,
+        "processed_queries.json",
+        &result.processed_queries,
+    )?;
+    write_json(&output_path, "geocode_areas.json", &result.geocode_areas)?;
+
+    println!("results written to {output_path:?}");
+
+    Ok(())
+}
+
+/// Parses a bbox in the `south,west,north,east` format used by Overpass
+fn parse_bbox(bbox: &str) -> Option<Bbox> {
+    let coords = bbox
+        .split(',')
+        .map(|c| c.trim().parse::<f32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let [south, west, north, east] = coords.as_slice() else {
+        return None;
+    };
+
+    Some(Bbox {
+        ne: [*north, *east],
+        sw: [*south, *west],
+    })
+}
+
+/// Replaces the characters that can't be part of a file name
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// File names for the layers. Map nodes can share a name, and since a layer would overwrite
+/// the file of an earlier one, repeated names get the index of the layer appended
+fn layer_file_names<'a>(names: impl Iterator<Item = &'a str>) -> Vec<String> {
+    // compared ignoring case, for case insensitive file systems
+    let mut used = HashSet::new();
+    names
+        .enumerate()
+        .map(|(i, name)| {
+            let name = file_name(name);
+            let mut unique = name.clone();
+            let mut suffix = i;
+            while !used.insert(unique.to_lowercase()) {
+                unique = format!("{name}_{suffix}");
+                suffix += 1;
+            }
+            format!("{unique}.geojson")
+        })
+        .collect()
+}
+
+fn write_json(folder: &Path, name: &str, value: &impl serde::Serialize) -> Result<(), RunError> {
+    let mut path = folder.to_path_buf();
+    path.push(name);
+    std::fs::write(path, serde_json::to_string(value)?)?;
+    Ok(())
+}
+
+/// A graph, as saved by the frontend
+#[derive(Deserialize)]
+struct SavedGraph {
+    version: String,
+    #[serde(flatten)]
+    graph: Graph,
+}
+
+#[derive(Error, Debug)]
+enum RunError {
+    #[error("DATA_PATH is not set\n{}", USAGE)]
+    DataPath,
+    #[error("unsupported graph version {0}, expected {}", GRAPH_VERSION)]
+    Version(String),
+    #[error("{0}")]
+    Search(#[from] SearchError),
+    #[error("{0:?}")]
+    Elevation(#[from] underpass::elevation::ElevationError),
+    #[error("{0:?}")]
+    Serde(#[from] serde_json::Error),
+    #[error("{0:?}")]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_file_names() {
+        let names = ["Peaks", "Huts", "peaks", "a/b", "a:b", "Peaks_2"];
+        assert_eq!(
+            layer_file_names(names.into_iter()),
+            vec![
+                "Peaks.geojson",
+                "Huts.geojson",
+                "peaks_2.geojson",
+                "a_b.geojson",
+                "a_b_4.geojson",
+                "Peaks_2_5.geojson",
+            ]
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use underpass::elevation::{elevation_path, write_index, ElevationError};
+
+use gdal::{raster::Buffer, Dataset, DriverManager};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Default width and height of the tiles, in pixels
+const DEFAULT_TILE_SIZE: usize = 4096;
+
+const USAGE: &str = "usage: update-elevation [--out <folder>] [--tile-size <pixels>] [raster...]
+
+splits every raster into GeoTIFF tiles in the output folder, which defaults to $DATA_PATH/elevation,
+and writes an index of all the rasters in it. without rasters, only the index is rewritten";
+
+fn main() {
+    // we only care if the error is a line parse
+    if let Err(err @ dotenv::Error::LineParse(..)) = dotenv::dotenv() {
+        panic!("{:?}", err);
+    }
+
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(err) = update_elevation(args) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
+
+#[derive(Debug)]
+struct Args {
+    out: PathBuf,
+    tile_size: usize,
+    rasters: Vec<PathBuf>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, UpdateElevationError> {
+        let mut out = None;
+        let mut tile_size = DEFAULT_TILE_SIZE;
+        let mut rasters = vec![];
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--out" => out = Some(args.next().ok_or(UpdateElevationError::Args)?.into()),
+                "--tile-size" => {
+                    tile_size = args
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .filter(|s| *s > 0)
+                        .ok_or(UpdateElevationError::Args)?
+                }
+                "-h" | "--help" => Err(UpdateElevationError::Args)?,
+                _ => rasters.push(arg.into()),
+            }
+        }
+
+        let out = match out {
+            Some(out) => out,
+            None => elevation_path(&PathBuf::from(
+                std::env::var("DATA_PATH").map_err(|_| UpdateElevationError::Args)?,
+            )),
+        };
+
+        Ok(Self {
+            out,
+            tile_size,
+            rasters,
+        })
+    }
+}
+
+fn update_elevation(args: Args) -> Result<(), UpdateElevationError> {
+    std::fs::create_dir_all(&args.out)?;
+
+    for raster in &args.rasters {
+        println!("tiling {raster:?}");
+        let tiles = create_tiles(raster, &args.out, args.tile_size)?;
+        println!("created {tiles} tiles from {raster:?}");
+    }
+
+    println!("indexing {:?}", args.out);
+    let indexed = write_index(&args.out)?;
+    println!("indexed {indexed} rasters");
+
+    Ok(())
+}
+
+/// Splits the first band of `raster` into `tile_size`x`tile_size` GeoTIFFs in `out`, named `<raster>_<x>_<y>.tif`.
+/// The tiles keep the CRS of the raster, and tiles without any data are skipped.
+/// Returns the amount of tiles written
+fn create_tiles(
+    raster: &Path,
+    out: &Path,
+    tile_size: usize,
+) -> Result<usize, UpdateElevationError> {
+    let source = Dataset::open(raster)?;
+    let band = source.rasterband(1)?;
+    let no_data = band.no_data_value();
+    let geo_transform = source.geo_transform()?;
+    let spatial_ref = source.spatial_ref().ok();
+    let (xsize, ysize) = source.raster_size();
+
+    let name = raster
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .ok_or_else(|| UpdateElevationError::Name(raster.to_path_buf()))?;
+
+    let driver = DriverManager::get_driver_by_name("GTiff")?;
+    let mut written = 0;
+
+    for (tx, x) in (0..xsize).step_by(tile_size).enumerate() {
+        for (ty, y) in (0..ysize).step_by(tile_size).enumerate() {
+            let width = tile_size.min(xsize - x);
+            let height = tile_size.min(ysize - y);
+
+            let buffer: Buffer<f32> = band.read_as(
+                (x as isize, y as isize),
+                (width, height),
+                (width, height),
+                None,
+            )?;
+
+            let has_data = buffer
+                .data
+                .iter()
+                .any(|v| !v.is_nan() && no_data != Some(*v as f64));
+            if !has_data {
+                continue;
+            }
+
+            let path = out.join(format!("{name}_{tx}_{ty}.tif"));
+            let mut tile =
+                driver.create_with_band_type::<f32, _>(&path, width as _, height as _, 1)?;
+
+            let [ulx, xres, xskew, uly, yskew, yres] = geo_transform;
+            tile.set_geo_transform(&[
+                ulx + x as f64 * xres + y as f64 * xskew,
+                xres,
+                xskew,
+                uly + x as f64 * yskew + y as f64 * yres,
+                yskew,
+                yres,
+            ])?;
+            if let Some(spatial_ref) = &spatial_ref {
+                tile.set_spatial_ref(spatial_ref)?;
+            }
+
+            let mut tile_band = tile.rasterband(1)?;
+            tile_band.set_no_data_value(no_data)?;
+            tile_band.write((0, 0), (width, height), &buffer)?;
+
+            written += 1;
+        }
+    }
+
+    Ok(written)
+}
+
+#[derive(Error, Debug)]
+enum UpdateElevationError {
+    #[error("invalid arguments")]
+    Args,
+    #[error("{0:?} doesn't have a file name")]
+    Name(PathBuf),
+    #[error("{0:?}")]
+    Gdal(#[from] gdal::errors::GdalError),
+    #[error("{0:?}")]
+    Elevation(#[from] ElevationError),
+    #[error("{0:?}")]
+    Io(#[from] std::io::Error),
+}
@@ -1,26 +1,127 @@
-use std::time::Duration;
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use ahash::RandomState;
 use geojson::FeatureCollection;
 use moka::future::Cache;
+use serde::{Deserialize, Serialize};
 
 use crate::search::{Bbox, GeocodeaArea};
 
 #[derive(Clone)]
 pub struct Caches {
     pub overpass: OverpassCache,
+    /// Second tier for `overpass`, which survives restarts
+    pub overpass_disk: Option<Arc<DiskCache>>,
+    pub stats: Arc<CacheStats>,
 }
 
 impl Caches {
     pub fn new() -> Self {
+        Self::with_config(&CacheConfig::default(), None)
+    }
+
+    /// Reads the config from the environment, storing the disk cache under `data_path`
+    pub fn from_env(data_path: &Path) -> Self {
+        let config = CacheConfig::from_env();
+
+        let disk = config.disk_max_size.and_then(|max_size| {
+            let path = overpass_cache_path(data_path);
+            match DiskCache::new(path.clone(), max_size, config.disk_ttl) {
+                Ok(disk) => Some(Arc::new(disk)),
+                Err(err) => {
+                    tracing::error!("failed to create disk cache at {path:?}: {err}");
+                    None
+                }
+            }
+        });
+
+        Self::with_config(&config, disk)
+    }
+
+    fn with_config(config: &CacheConfig, overpass_disk: Option<Arc<DiskCache>>) -> Self {
         let overpass = Cache::builder()
-            .max_capacity(100)
-            .time_to_live(Duration::from_secs(30 * 60))
+            .max_capacity(config.memory_capacity)
+            .time_to_live(config.memory_ttl)
             .time_to_idle(Duration::from_secs(10 * 60))
             // we use ahash because it's faster for big keys
             .build_with_hasher(ahash::RandomState::default());
 
-        Self { overpass }
+        Self {
+            overpass,
+            overpass_disk,
+            stats: Default::default(),
+        }
+    }
+
+    /// Returns the cached results for `key`, looking in memory first and on disk second.
+    /// Runs `init` if neither has it, and stores the results in both
+    pub async fn get_overpass<E, F>(
+        &self,
+        key: OverpassKey,
+        init: F,
+    ) -> Result<OverpassValue, Arc<E>>
+    where
+        F: Future<Output = Result<OverpassValue, E>>,
+        E: Send + Sync + 'static,
+    {
+        if self.overpass.contains_key(&key) {
+            self.stats.memory_hits.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let disk = self.overpass_disk.as_deref();
+        let stats = &self.stats;
+        self.overpass
+            .try_get_with(key.clone(), async move {
+                if let Some(disk) = disk {
+                    if let Some(value) = disk.get(&key).await {
+                        stats.disk_hits.fetch_add(1, Ordering::Relaxed);
+                        return Ok(value);
+                    }
+                }
+
+                stats.misses.fetch_add(1, Ordering::Relaxed);
+                let value = init.await?;
+
+                if let Some(disk) = disk {
+                    disk.insert(&key, &value).await;
+                }
+
+                Ok(value)
+            })
+            .await
+    }
+
+    pub async fn stats(&self) -> CacheStatsReport {
+        let (disk_entries, disk_bytes, disk_evictions) = match &self.overpass_disk {
+            Some(disk) => {
+                let entries = disk.entries().await;
+                (
+                    entries.len() as u64,
+                    entries.iter().map(|e| e.size).sum(),
+                    disk.evictions.load(Ordering::Relaxed),
+                )
+            }
+            None => (0, 0, 0),
+        };
+
+        CacheStatsReport {
+            memory_entries: self.overpass.entry_count(),
+            memory_hits: self.stats.memory_hits.load(Ordering::Relaxed),
+            disk_enabled: self.overpass_disk.is_some(),
+            disk_entries,
+            disk_bytes,
+            disk_hits: self.stats.disk_hits.load(Ordering::Relaxed),
+            disk_evictions,
+            misses: self.stats.misses.load(Ordering::Relaxed),
+        }
     }
 }
 
@@ -30,5 +131,352 @@ impl Default for Caches {
     }
 }
 
-pub type OverpassCache =
-    Cache<(String, Bbox), (FeatureCollection, Vec<GeocodeaArea>, String), RandomState>;
+pub type OverpassKey = (String, Bbox);
+/// (Results, Geocode areas, Processed query, Endpoint)
+pub type OverpassValue = (FeatureCollection, Vec<GeocodeaArea>, String, String);
+
+/// (Query, Bbox) -> (Results, Geocode areas, Processed query, Endpoint)
+pub type OverpassCache = Cache<OverpassKey, OverpassValue, RandomState>;
+
+pub fn overpass_cache_path(data_path: &Path) -> PathBuf {
+    let mut path = data_path.to_path_buf();
+    path.push("overpass_cache");
+    path
+}
+
+pub struct CacheConfig {
+    /// Max number of entries kept in memory
+    pub memory_capacity: u64,
+    pub memory_ttl: Duration,
+    /// Max size of the disk cache in bytes. The disk cache is disabled if `None`
+    pub disk_max_size: Option<u64>,
+    pub disk_ttl: Duration,
+}
+
+impl CacheConfig {
+    /// Reads the config from the following environment variables, using the defaults for the missing ones:
+    /// - `OVERPASS_CACHE_CAPACITY`: max entries in memory
+    /// - `OVERPASS_CACHE_TTL`: minutes to keep entries in memory
+    /// - `OVERPASS_DISK_CACHE_SIZE`: max size of the disk cache in megabytes, enables the disk cache
+    /// - `OVERPASS_DISK_CACHE_TTL`: hours to keep entries on disk
+    pub fn from_env() -> Self {
+        fn var(name: &str) -> Option<u64> {
+            let value = std::env::var(name).ok()?;
+            match value.trim().parse() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    tracing::warn!("ignoring invalid {name}: {value:?}");
+                    None
+                }
+            }
+        }
+
+        let default = Self::default();
+        Self {
+            memory_capacity: var("OVERPASS_CACHE_CAPACITY").unwrap_or(default.memory_capacity),
+            memory_ttl: var("OVERPASS_CACHE_TTL")
+                .map(|m| Duration::from_secs(m * 60))
+                .unwrap_or(default.memory_ttl),
+            disk_max_size: var("OVERPASS_DISK_CACHE_SIZE")
+                .filter(|mb| *mb > 0)
+                .map(|mb| mb * 1024 * 1024),
+            disk_ttl: var("OVERPASS_DISK_CACHE_TTL")
+                .map(|h| Duration::from_secs(h * 60 * 60))
+                .unwrap_or(default.disk_ttl),
+        }
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            memory_capacity: 100,
+            memory_ttl: Duration::from_secs(30 * 60),
+            disk_max_size: None,
+            disk_ttl: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CacheStats {
+    memory_hits: AtomicU64,
+    disk_hits: AtomicU64,
+    /// Requests that weren't in any cache, and had to be sent to Overpass
+    misses: AtomicU64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CacheStatsReport {
+    pub memory_entries: u64,
+    pub memory_hits: u64,
+    pub disk_enabled: bool,
+    pub disk_entries: u64,
+    pub disk_bytes: u64,
+    pub disk_hits: u64,
+    pub disk_evictions: u64,
+    pub misses: u64,
+}
+
+/// Stores Overpass results as one json file per query, evicting the least recently used files
+/// once the folder is bigger than `max_size`
+///
+/// The modification time of the files is updated on every read, and is what we use to find the least recently used ones
+pub struct DiskCache {
+    path: PathBuf,
+    max_size: u64,
+    ttl: Duration,
+    /// only one eviction runs at a time
+    evicting: tokio::sync::Mutex<()>,
+    evictions: AtomicU64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    /// unix timestamp in seconds
+    created: u64,
+    query: String,
+    bbox: Bbox,
+    value: OverpassValue,
+}
+
+struct EntryFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl DiskCache {
+    pub fn new(path: PathBuf, max_size: u64, ttl: Duration) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&path)?;
+
+        Ok(Self {
+            path,
+            max_size,
+            ttl,
+            evicting: Default::default(),
+            evictions: Default::default(),
+        })
+    }
+
+    fn entry_path(&self, (query, bbox): &OverpassKey) -> PathBuf {
+        // the std hashers can change between Rust releases, which would orphan every entry
+        let bbox_bits = [bbox.ne[0], bbox.ne[1], bbox.sw[0], bbox.sw[1]].map(f32::to_bits);
+        let hash = fnv1a(
+            query
+                .bytes()
+                .chain(bbox_bits.iter().flat_map(|bits| bits.to_le_bytes())),
+        );
+
+        let mut path = self.path.clone();
+        path.push(format!("{hash:016x}.json"));
+        path
+    }
+
+    pub async fn get(&self, key: &OverpassKey) -> Option<OverpassValue> {
+        let path = self.entry_path(key);
+        let bytes = tokio::fs::read(&path).await.ok()?;
+
+        let entry: DiskEntry = match serde_json::from_slice(&bytes) {
+            Ok(entry) => entry,
+            Err(err) => {
+                tracing::warn!("removing unreadable cache entry {path:?}: {err}");
+                let _ = tokio::fs::remove_file(&path).await;
+                return None;
+            }
+        };
+
+        // the hash could collide
+        if entry.query != key.0 || entry.bbox != key.1 {
+            return None;
+        }
+
+        if now() > entry.created + self.ttl.as_secs() {
+            let _ = tokio::fs::remove_file(&path).await;
+            return None;
+        }
+
+        // mark as recently used
+        let touched = tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || set_modified(&path, SystemTime::now())
+        })
+        .await
+        .map_err(std::io::Error::from)
+        .and_then(|touched| touched);
+        if let Err(err) = touched {
+            tracing::debug!("failed to update modification time of {path:?}: {err}");
+        }
+
+        Some(entry.value)
+    }
+
+    /// Errors are only logged, since the results are still valid without the disk cache
+    pub async fn insert(&self, key: &OverpassKey, value: &OverpassValue) {
+        let path = self.entry_path(key);
+
+        let entry = DiskEntry {
+            created: now(),
+            query: key.0.clone(),
+            bbox: key.1,
+            value: value.clone(),
+        };
+        let bytes = match serde_json::to_vec(&entry) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                tracing::warn!("failed to serialize cache entry: {err}");
+                return;
+            }
+        };
+
+        // write to a temporary file first, so readers never see half written entries.
+        // concurrent inserts of the same key each get their own
+        let tmp = path.with_extension(format!("{:016x}.tmp", rand::random::<u64>()));
+        let written = async {
+            tokio::fs::write(&tmp, bytes).await?;
+            tokio::fs::rename(&tmp, &path).await
+        };
+        if let Err(err) = written.await {
+            tracing::warn!("failed to write cache entry {path:?}: {err}");
+            return;
+        }
+
+        self.evict().await;
+    }
+
+    async fn entries(&self) -> Vec<EntryFile> {
+        let mut entries = vec![];
+
+        let Ok(mut dir) = tokio::fs::read_dir(&self.path).await else {
+            return entries;
+        };
+        while let Ok(Some(file)) = dir.next_entry().await {
+            let path = file.path();
+            if path.extension() != Some("json".as_ref()) {
+                continue;
+            }
+            let Ok(metadata) = file.metadata().await else {
+                continue;
+            };
+
+            entries.push(EntryFile {
+                path,
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+            });
+        }
+
+        entries
+    }
+
+    /// Removes expired entries, and then the least recently used ones until the cache fits in `max_size`
+    async fn evict(&self) {
+        let Ok(_guard) = self.evicting.try_lock() else {
+            // someone else is already evicting
+            return;
+        };
+
+        let mut entries = self.entries().await;
+        // most recently used first
+        entries.sort_by_key(|e| std::cmp::Reverse(e.modified));
+
+        let expired_before = SystemTime::now()
+            .checked_sub(self.ttl)
+            .unwrap_or(UNIX_EPOCH);
+        let mut size = 0;
+        for entry in entries {
+            size += entry.size;
+
+            // entries are created before they are last used, so an old modification time means it's expired
+            if size <= self.max_size && entry.modified >= expired_before {
+                continue;
+            }
+
+            match tokio::fs::remove_file(&entry.path).await {
+                Ok(()) => {
+                    self.evictions.fetch_add(1, Ordering::Relaxed);
+                    size -= entry.size;
+                }
+                Err(err) => tracing::warn!("failed to evict {:?}: {err}", entry.path),
+            }
+        }
+    }
+}
+
+/// tokio doesn't have a way to set modification times, so this is blocking
+fn set_modified(path: &Path, time: SystemTime) -> std::io::Result<()> {
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(time)
+}
+
+/// 64 bit FNV-1a, which unlike the std hashers is the same in every build
+fn fnv1a(bytes: impl Iterator<Item = u8>) -> u64 {
+    bytes.fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(query: &str) -> OverpassValue {
+        let collection = FeatureCollection {
+            bbox: None,
+            features: vec![],
+            foreign_members: None,
+        };
+        (
+            collection,
+            vec![],
+            query.to_string(),
+            "endpoint".to_string(),
+        )
+    }
+
+    #[test]
+    fn disk_cache_entry_path_is_stable() {
+        // changing the file names would orphan every entry already on disk
+        let disk = DiskCache::new(std::env::temp_dir(), 0, Duration::ZERO).unwrap();
+        let path = disk.entry_path(&("a".to_string(), Bbox::default()));
+        assert_eq!(path.file_name().unwrap(), "da0b881500262f8c.json");
+    }
+
+    #[tokio::test]
+    async fn disk_cache_evicts_least_recently_used() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("underpass-cache-{}", rand::random::<u64>()));
+
+        let a = ("a".to_string(), Bbox::default());
+        let b = ("b".to_string(), Bbox::default());
+
+        // a single entry is around 150 bytes, so only one fits
+        let disk = DiskCache::new(path.clone(), 200, Duration::from_secs(60)).unwrap();
+        disk.insert(&a, &value("a")).await;
+        assert_eq!(disk.get(&a).await.unwrap().2, "a");
+        assert!(disk.get(&b).await.is_none());
+
+        // make sure b is more recent than a, even with coarse modification times
+        set_modified(
+            &disk.entry_path(&a),
+            SystemTime::now() - Duration::from_secs(30),
+        )
+        .unwrap();
+        disk.insert(&b, &value("b")).await;
+
+        assert!(disk.get(&a).await.is_none());
+        assert_eq!(disk.get(&b).await.unwrap().2, "b");
+        assert_eq!(disk.evictions.load(Ordering::Relaxed), 1);
+
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -1,141 +1,621 @@
 use std::{
-    collections::HashMap,
-    io,
+    collections::{hash_map::Entry, HashMap},
+    fs, io,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
 };
 
-use gdal::{raster::ResampleAlg, Dataset};
+use gdal::{
+    raster::ResampleAlg,
+    spatial_ref::{CoordTransform, SpatialRef},
+    Dataset,
+};
 use rtree_rs::{RTree, Rect};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+/// How to calculate the elevation of a point that falls between pixel centers
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Interpolation {
+    /// value of the pixel the point falls in
+    Nearest,
+    /// weighted average of the 4 closest pixels
+    #[default]
+    Bilinear,
+    /// cubic convolution of the 16 closest pixels
+    Cubic,
+}
+
+/// Returns the path of the elevation dataset folder, based on the path of the data folder
+pub fn elevation_path(data_path: &Path) -> PathBuf {
+    let mut elevation_path = data_path.to_path_buf();
+    elevation_path.push("elevation");
+    elevation_path
+}
+
+/// Returns the elevation sources listed in `ELEVATION_SOURCES`, separated by commas, in priority order.
+/// Defaults to the elevation dataset folder
+pub fn elevation_sources(data_path: &Path) -> Vec<PathBuf> {
+    match std::env::var("ELEVATION_SOURCES") {
+        Ok(sources) if !sources.trim().is_empty() => sources
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(PathBuf::from)
+            .collect(),
+        _ => vec![elevation_path(data_path)],
+    }
+}
+
+/// Width and height in pixels of the blocks of raster data we keep in memory
+const BLOCK_SIZE: usize = 256;
+/// Default size of the block cache, in bytes
+pub const DEFAULT_CACHE_SIZE: u64 = 256 * 1024 * 1024;
+/// Extensions of the raster files we load from elevation folders
+const RASTER_EXTENSIONS: [&str; 3] = ["tif", "tiff", "vrt"];
+/// Name of the manifest of an elevation folder, listing its rasters so they don't have to be opened on startup
+pub const INDEX_FILE: &str = "index.json";
+/// EPSG:4326, but always in longitude, latitude order
+const WGS84: &str = "+proj=longlat +datum=WGS84 +no_defs";
+
 pub struct ElevationMap {
-    tree: RTree<2, f64, PathBuf>,
+    tree: RTree<2, f64, Tile>,
+    /// blocks of raster data, shared between every request
+    blocks: moka::sync::Cache<BlockKey, Arc<Block>>,
+}
+
+/// A raster file, with the metadata we need to find pixels in it
+#[derive(Debug, PartialEq)]
+struct Tile {
+    /// unique per tile, used to identify its blocks
+    id: usize,
+    path: PathBuf,
+    /// index of the source the tile comes from. lower goes first
+    priority: usize,
+    geo_transform: [f64; 6],
+    size: (usize, usize),
+    no_data: Option<f64>,
+    /// whether the raster isn't in EPSG:4326, and coordinates have to be reprojected to look it up
+    reproject: bool,
+}
+
+/// (Tile id, block x, block y)
+type BlockKey = (usize, usize, usize);
+
+/// A `BLOCK_SIZE`x`BLOCK_SIZE` window of a raster, or smaller at the edges.
+/// No-data pixels are stored as NaN
+struct Block {
+    width: usize,
+    data: Vec<f32>,
 }
 
 impl ElevationMap {
     pub fn new(path: &Path) -> Result<Self, ElevationError> {
+        Self::with_cache_size(path, DEFAULT_CACHE_SIZE)
+    }
+
+    /// `cache_size` is the max amount of bytes of raster data kept in memory
+    pub fn with_cache_size(path: &Path, cache_size: u64) -> Result<Self, ElevationError> {
+        Self::from_sources(&[path.to_path_buf()], cache_size)
+    }
+
+    /// Loads every source, which can be a folder of rasters, or a single raster such as a VRT mosaic or a Cloud-Optimized GeoTIFF.
+    /// GDAL virtual paths like `/vsicurl/https://...` are also supported.
+    ///
+    /// Where sources overlap, the first one that has data for a point is used,
+    /// so higher resolution sources should go first
+    pub fn from_sources(sources: &[PathBuf], cache_size: u64) -> Result<Self, ElevationError> {
         let mut tree = RTree::new();
+        let mut id = 0;
 
-        if path.exists() {
-            for entry in path.read_dir()?.flatten() {
-                if entry.file_name().to_string_lossy().ends_with(".tif") {
-                    let corner_coords = get_coorner_coords(&entry.path())?;
-                    tree.insert(
-                        Rect::new(corner_coords.bottom_left, corner_coords.top_right),
-                        entry.path(),
-                    );
-                }
+        for (priority, source) in sources.iter().enumerate() {
+            let (paths, mut index) = if source.is_dir() {
+                (raster_paths(source)?, read_index(source))
+            } else if source.exists() || source.to_string_lossy().starts_with("/vsi") {
+                (vec![source.clone()], HashMap::new())
+            } else {
+                tracing::error!("{source:?} doesn't exist, skipping elevation source");
+                continue;
+            };
+
+            for path in paths {
+                // rasters missing from the index, or changed after it was written, have to be opened
+                let (tile, corner_coords) = match index.remove(&path) {
+                    Some((entry, modified)) if !is_newer(&path, modified) => {
+                        entry.into_tile(id, path, priority)
+                    }
+                    _ => open_tile(id, path, priority)?,
+                };
+                tree.insert(
+                    Rect::new(corner_coords.bottom_left, corner_coords.top_right),
+                    tile,
+                );
+                id += 1;
             }
-        } else {
-            tracing::error!("{path:?} doesn't exist, constructing empty ElevationMap");
         }
 
-        Ok(Self { tree })
+        if id == 0 {
+            tracing::error!(
+                "no elevation rasters found in {sources:?}, constructing empty ElevationMap"
+            );
+        }
+
+        let blocks = moka::sync::Cache::builder()
+            .max_capacity(cache_size)
+            .weigher(|_, block: &Arc<Block>| (block.data.len() * std::mem::size_of::<f32>()) as u32)
+            .build();
+
+        Ok(Self { tree, blocks })
     }
 
     pub fn cached(&self) -> CachedElevationMap<'_> {
+        self.cached_with(Interpolation::default())
+    }
+
+    pub fn cached_with(&self, interpolation: Interpolation) -> CachedElevationMap<'_> {
         CachedElevationMap {
             map: self,
+            interpolation,
             cache: HashMap::new(),
+            transforms: HashMap::new(),
         }
     }
 
-    /// looks up the elevation for the given coordinates, or returns 0 if there's an error
+    /// looks up the elevation for the given coordinates, or returns 0 if there's no data
     #[allow(dead_code)]
-    pub fn lookup_or_0(&self, lng: f64, lat: f64) -> i32 {
-        self.lookup(lng, lat).unwrap_or(0)
+    pub fn lookup_or_0(&self, lng: f64, lat: f64) -> f64 {
+        self.lookup(lng, lat).unwrap_or(0.0)
     }
 
-    /// looks up the elevation for the given coordinates
+    /// looks up the elevation for the given coordinates, returning `None` if there's no data for them
     #[allow(dead_code)]
-    pub fn lookup(&self, lng: f64, lat: f64) -> Result<i32, ElevationError> {
-        let path = self.dataset_path(lng, lat)?;
-        let data = Dataset::open(path)?;
-        lookup(&data, lng, lat)
+    pub fn lookup(&self, lng: f64, lat: f64) -> Option<f64> {
+        self.cached().lookup(lng, lat)
     }
 
+    /// path of the raster with the highest priority for the given coordinates
     pub fn dataset_path(&self, lng: f64, lat: f64) -> Result<&Path, ElevationError> {
-        Ok(self
+        let tile = self
+            .tiles(lng, lat)
+            .into_iter()
+            .next()
+            .ok_or(ElevationError::CoordNotFound)?;
+        Ok(&tile.path)
+    }
+
+    /// tiles that contain the given coordinates, sorted by priority
+    fn tiles(&self, lng: f64, lat: f64) -> Vec<&Tile> {
+        let mut tiles = self
             .tree
             .search(Rect::new_point([lng, lat]))
-            .next()
-            .ok_or(ElevationError::CoordNotFound)?
-            .data)
+            .map(|item| item.data)
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|t| (t.priority, t.id));
+        tiles
+    }
+
+    /// number of raster blocks kept in memory
+    pub fn cached_blocks(&self) -> u64 {
+        self.blocks.entry_count()
     }
 }
 
+/// Looks up elevations for a single task.
+///
+/// Raster data comes from the block cache shared by the whole [`ElevationMap`],
+/// and the datasets opened to fill it are kept in `cache` until this is dropped.
+/// Lookups do blocking io, so this should be used outside of the async runtime
 pub struct CachedElevationMap<'a> {
     map: &'a ElevationMap,
+    interpolation: Interpolation,
     pub cache: HashMap<PathBuf, Dataset>,
+    /// Tile id -> Transformation from EPSG:4326 into the CRS of the tile
+    transforms: HashMap<usize, CoordTransform>,
 }
 
 impl<'a> CachedElevationMap<'a> {
-    /// looks up the elevation for the given coordinates, or returns 0 if there's an error
-    pub fn lookup_or_0(&mut self, lng: f64, lat: f64) -> i32 {
-        self.lookup(lng, lat).unwrap_or(0)
+    /// looks up the elevation for the given coordinates, or returns 0 if there's no data
+    pub fn lookup_or_0(&mut self, lng: f64, lat: f64) -> f64 {
+        self.lookup(lng, lat).unwrap_or(0.0)
+    }
+
+    /// looks up the elevation for the given coordinates, returning `None` if there's no data for them
+    pub fn lookup(&mut self, lng: f64, lat: f64) -> Option<f64> {
+        match self.try_lookup(lng, lat) {
+            Ok(elevation) => elevation,
+            Err(ElevationError::CoordNotFound) => None,
+            Err(err) => {
+                tracing::warn!("failed to look up elevation for {lng},{lat}: {err}");
+                None
+            }
+        }
     }
 
     /// looks up the elevation for the given coordinates
-    pub fn lookup(&mut self, lng: f64, lat: f64) -> Result<i32, ElevationError> {
-        let path = self.map.dataset_path(lng, lat)?;
+    ///
+    /// returns `Ok(None)` if the coordinates fall on a no-data pixel
+    ///
+    /// tiles are tried in priority order, so a point on a no-data pixel falls through to the next tile covering it
+    pub fn try_lookup(&mut self, lng: f64, lat: f64) -> Result<Option<f64>, ElevationError> {
+        let map = self.map;
+        let tiles = map.tiles(lng, lat);
+        if tiles.is_empty() {
+            return Err(ElevationError::CoordNotFound);
+        }
+
+        for tile in tiles {
+            if let Some(elevation) = self.tile_lookup(tile, lng, lat)? {
+                return Ok(Some(elevation));
+            }
+        }
+
+        Ok(None)
+    }
 
-        if let Some(data) = self.cache.get(path) {
-            tracing::trace!("cache hit: dataset {path:?} was in cache");
-            lookup(data, lng, lat)
+    fn tile_lookup(
+        &mut self,
+        tile: &Tile,
+        lng: f64,
+        lat: f64,
+    ) -> Result<Option<f64>, ElevationError> {
+        let (x, y) = if tile.reproject {
+            self.reproject(tile, lng, lat)?
         } else {
-            tracing::trace!("cache miss: dataset {path:?} was not in cache");
-            let data = Dataset::open(path)?;
-            let r = lookup(&data, lng, lat);
-            self.cache.insert(path.to_path_buf(), data);
+            (lng, lat)
+        };
+        let gt = tile.geo_transform;
+
+        // position in pixels, where pixel centers are at .5
+        // https://stackoverflow.com/questions/13439357/extract-point-from-raster-in-gdal
+        let px = (x - gt[0]) / gt[1];
+        let py = (y - gt[3]) / gt[5];
+
+        // the bounds of reprojected tiles are bigger than the raster itself
+        let (xsize, ysize) = tile.size;
+        if px < 0.0 || py < 0.0 || px >= xsize as f64 || py >= ysize as f64 {
+            return Ok(None);
+        }
+
+        let is_valid = |v: &f64| !v.is_nan();
+
+        let elevation = match self.interpolation {
+            Interpolation::Nearest => self.nearest(tile, px, py)?,
+            Interpolation::Bilinear => {
+                let (window, fx, fy) = self.window(tile, 2, px - 0.5, py - 0.5)?;
+                match <[f64; 4]>::try_from(window) {
+                    Ok(window) if window.iter().all(is_valid) => Some(bilinear(window, fx, fy)),
+                    // fall back to the closest pixel when some neighbours have no data, or the raster is too small
+                    _ => self.nearest(tile, px, py)?,
+                }
+            }
+            Interpolation::Cubic => {
+                let (window, fx, fy) = self.window(tile, 4, px - 1.5, py - 1.5)?;
+                match <[f64; 16]>::try_from(window) {
+                    Ok(window) if window.iter().all(is_valid) => Some(cubic(window, fx, fy)),
+                    _ => self.nearest(tile, px, py)?,
+                }
+            }
+        };
+
+        Ok(elevation)
+    }
+
+    /// transforms EPSG:4326 coordinates into the CRS of the tile
+    fn reproject(&mut self, tile: &Tile, lng: f64, lat: f64) -> Result<(f64, f64), ElevationError> {
+        let transform = match self.transforms.entry(tile.id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let srs = dataset(&mut self.cache, tile)?.spatial_ref()?;
+                entry.insert(CoordTransform::new(&wgs84()?, &srs)?)
+            }
+        };
 
-            r
+        let (mut x, mut y) = ([lng], [lat]);
+        transform.transform_coords(&mut x, &mut y, &mut [])?;
+        Ok((x[0], y[0]))
+    }
+
+    fn nearest(&mut self, tile: &Tile, px: f64, py: f64) -> Result<Option<f64>, ElevationError> {
+        let (window, _, _) = self.window(tile, 1, px, py)?;
+        Ok(window.into_iter().find(|v| !v.is_nan()))
+    }
+
+    /// reads a `size`x`size` window starting at the given pixel, moving it inside the raster if needed.
+    /// returns the window, and the position of the point relative to its top left pixel center
+    fn window(
+        &mut self,
+        tile: &Tile,
+        size: usize,
+        x: f64,
+        y: f64,
+    ) -> Result<(Vec<f64>, f64, f64), ElevationError> {
+        let (xsize, ysize) = tile.size;
+        let size = size.min(xsize).min(ysize);
+        let x0 = x.floor().clamp(0.0, (xsize - size) as f64);
+        let y0 = y.floor().clamp(0.0, (ysize - size) as f64);
+
+        let mut window = Vec::with_capacity(size * size);
+        for py in y0 as usize..y0 as usize + size {
+            for px in x0 as usize..x0 as usize + size {
+                window.push(self.pixel(tile, px, py)?);
+            }
         }
+
+        Ok((window, x - x0, y - y0))
     }
+
+    fn pixel(&mut self, tile: &Tile, x: usize, y: usize) -> Result<f64, ElevationError> {
+        let key = (tile.id, x / BLOCK_SIZE, y / BLOCK_SIZE);
+
+        let datasets = &mut self.cache;
+        let block = self.map.blocks.try_get_with(key, || {
+            read_block(datasets, tile, key.1, key.2).map(Arc::new)
+        })?;
+
+        let (bx, by) = (x % BLOCK_SIZE, y % BLOCK_SIZE);
+        Ok(block.data[by * block.width + bx] as f64)
+    }
+}
+
+/// opens the dataset of a tile, or returns it from `datasets` if it's already open
+fn dataset<'d>(
+    datasets: &'d mut HashMap<PathBuf, Dataset>,
+    tile: &Tile,
+) -> Result<&'d mut Dataset, ElevationError> {
+    Ok(match datasets.entry(tile.path.clone()) {
+        Entry::Occupied(entry) => {
+            tracing::trace!("cache hit: dataset {:?} was in cache", tile.path);
+            entry.into_mut()
+        }
+        Entry::Vacant(entry) => {
+            tracing::trace!("cache miss: dataset {:?} was not in cache", tile.path);
+            entry.insert(Dataset::open(&tile.path)?)
+        }
+    })
+}
+
+fn read_block(
+    datasets: &mut HashMap<PathBuf, Dataset>,
+    tile: &Tile,
+    block_x: usize,
+    block_y: usize,
+) -> Result<Block, ElevationError> {
+    let data = dataset(datasets, tile)?;
+
+    let (xsize, ysize) = tile.size;
+    let x = block_x * BLOCK_SIZE;
+    let y = block_y * BLOCK_SIZE;
+    let width = BLOCK_SIZE.min(xsize - x);
+    let height = BLOCK_SIZE.min(ysize - y);
+
+    let band = data.rasterband(1)?;
+    let window = band.read_as_array::<f64>(
+        (x as isize, y as isize),
+        (width, height),
+        (width, height),
+        Some(ResampleAlg::NearestNeighbour),
+    )?;
+
+    let data = window
+        .into_raw_vec()
+        .into_iter()
+        .map(|v| match tile.no_data {
+            Some(no_data) if v == no_data => f32::NAN,
+            _ => v as f32,
+        })
+        .collect();
+
+    Ok(Block { width, data })
+}
+
+/// Opens every raster in `dir`, and writes an [`INDEX_FILE`] listing them,
+/// so loading the folder doesn't have to open each one of them. Returns the amount of rasters indexed
+pub fn write_index(dir: &Path) -> Result<usize, ElevationError> {
+    let mut entries = raster_paths(dir)?
+        .into_iter()
+        .map(|path| {
+            let (tile, corner_coords) = open_tile(0, path, 0)?;
+            Ok(IndexEntry::new(&tile, &corner_coords))
+        })
+        .collect::<Result<Vec<_>, ElevationError>>()?;
+    entries.sort_by(|a, b| a.file.cmp(&b.file));
+
+    fs::write(dir.join(INDEX_FILE), serde_json::to_vec_pretty(&entries)?)?;
+
+    Ok(entries.len())
+}
+
+/// Rasters of an elevation folder, not including subfolders
+fn raster_paths(dir: &Path) -> Result<Vec<PathBuf>, ElevationError> {
+    Ok(dir
+        .read_dir()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|e| RASTER_EXTENSIONS.iter().any(|r| e == *r))
+        })
+        .collect())
 }
 
-fn get_coorner_coords(path: &Path) -> Result<CornerCoords, ElevationError> {
-    let data = Dataset::open(path)?;
-    let [ulx, xres, _xskew, uly, _yskew, yres] = data.geo_transform()?;
+/// Reads the [`INDEX_FILE`] of `dir`, returning its entries by path along with when it was written.
+/// Missing or invalid indexes are treated as empty
+fn read_index(dir: &Path) -> HashMap<PathBuf, (IndexEntry, SystemTime)> {
+    let path = dir.join(INDEX_FILE);
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    let read = || -> Result<_, ElevationError> {
+        let modified = fs::metadata(&path)?.modified()?;
+        let entries: Vec<IndexEntry> = serde_json::from_slice(&fs::read(&path)?)?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| (dir.join(&entry.file), (entry, modified)))
+            .collect())
+    };
+
+    read().unwrap_or_else(|err| {
+        tracing::warn!("failed to read elevation index {path:?}, ignoring it: {err}");
+        HashMap::new()
+    })
+}
+
+/// whether `path` was modified after `time`
+fn is_newer(path: &Path, time: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map_or(true, |modified| modified > time)
+}
+
+/// What we need to know about a raster to load it without opening it
+#[derive(Serialize, Deserialize, Debug)]
+struct IndexEntry {
+    /// file name, relative to the folder of the index
+    file: String,
+    geo_transform: [f64; 6],
+    size: (usize, usize),
+    no_data: Option<f64>,
+    reproject: bool,
+    /// bounds in EPSG:4326, as `[min lng, min lat, max lng, max lat]`
+    bounds: [f64; 4],
+}
+
+impl IndexEntry {
+    fn new(tile: &Tile, corner_coords: &CornerCoords) -> Self {
+        let [min_lng, min_lat] = corner_coords.bottom_left;
+        let [max_lng, max_lat] = corner_coords.top_right;
+        Self {
+            file: tile
+                .path
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            geo_transform: tile.geo_transform,
+            size: tile.size,
+            no_data: tile.no_data,
+            reproject: tile.reproject,
+            bounds: [min_lng, min_lat, max_lng, max_lat],
+        }
+    }
+
+    fn into_tile(self, id: usize, path: PathBuf, priority: usize) -> (Tile, CornerCoords) {
+        let [min_lng, min_lat, max_lng, max_lat] = self.bounds;
+        let tile = Tile {
+            id,
+            path,
+            priority,
+            geo_transform: self.geo_transform,
+            size: self.size,
+            no_data: self.no_data,
+            reproject: self.reproject,
+        };
+        let corner_coords = CornerCoords {
+            top_right: [max_lng, max_lat],
+            bottom_left: [min_lng, min_lat],
+        };
+        (tile, corner_coords)
+    }
+}
+
+/// EPSG:4326, in longitude, latitude order
+fn wgs84() -> Result<SpatialRef, ElevationError> {
+    Ok(SpatialRef::from_proj4(WGS84)?)
+}
+
+fn open_tile(
+    id: usize,
+    path: PathBuf,
+    priority: usize,
+) -> Result<(Tile, CornerCoords), ElevationError> {
+    let data = Dataset::open(&path)?;
+    let geo_transform = data.geo_transform()?;
+    let [ulx, xres, _xskew, uly, _yskew, yres] = geo_transform;
 
     let (xsize, ysize) = data.raster_size();
 
     let lrx = ulx + (xsize as f64 * xres);
     let lry = uly + (ysize as f64 * yres);
 
-    Ok(CornerCoords {
-        // top_left: (ulx, uly),
-        top_right: [lrx, uly],
-        bottom_left: [ulx, lry],
-        // bottom_right: (lrx, lry),
-    })
+    let no_data = data.rasterband(1)?.no_data_value();
+
+    // rasters without a CRS are assumed to be in EPSG:4326, like SRTM tiles
+    let srs = data
+        .spatial_ref()
+        .ok()
+        .filter(|srs| !(srs.is_geographic() && srs.auth_code().ok() == Some(4326)));
+
+    let corner_coords = match &srs {
+        None => CornerCoords {
+            top_right: [lrx, uly],
+            bottom_left: [ulx, lry],
+        },
+        Some(srs) => {
+            // the bounding box of the reprojected corners, which can be a bit bigger than the raster
+            let transform = CoordTransform::new(srs, &wgs84()?)?;
+            let mut xs = [ulx, lrx, ulx, lrx];
+            let mut ys = [uly, uly, lry, lry];
+            transform.transform_coords(&mut xs, &mut ys, &mut [])?;
+
+            let min = |v: [f64; 4]| v.into_iter().fold(f64::INFINITY, f64::min);
+            let max = |v: [f64; 4]| v.into_iter().fold(f64::NEG_INFINITY, f64::max);
+            CornerCoords {
+                top_right: [max(xs), max(ys)],
+                bottom_left: [min(xs), min(ys)],
+            }
+        }
+    };
+
+    let tile = Tile {
+        id,
+        path,
+        priority,
+        geo_transform,
+        size: (xsize, ysize),
+        no_data,
+        reproject: srs.is_some(),
+    };
+
+    Ok((tile, corner_coords))
 }
 
 #[derive(Debug)]
 struct CornerCoords {
-    // top_left: (f64, f64),
     top_right: [f64; 2],
     bottom_left: [f64; 2],
-    // bottom_right: (f64, f64),
 }
 
-// https://stackoverflow.com/questions/13439357/extract-point-from-raster-in-gdal
-fn lookup(data: &Dataset, lng: f64, lat: f64) -> Result<i32, ElevationError> {
-    let gt = data.geo_transform()?;
+/// interpolates between the 2x2 `window`, where `fx` and `fy` go from 0 to 1 between pixel centers
+fn bilinear(window: [f64; 4], fx: f64, fy: f64) -> f64 {
+    let fx = fx.clamp(0.0, 1.0);
+    let fy = fy.clamp(0.0, 1.0);
 
-    // unsure where this came from. i found it somewhere but i cant find it again
-    let x = ((lng - gt[0]) / gt[1]) as i64;
-    let y = ((lat - gt[3]) / gt[5]) as i64;
+    let top = window[0] * (1.0 - fx) + window[1] * fx;
+    let bottom = window[2] * (1.0 - fx) + window[3] * fx;
+    top * (1.0 - fy) + bottom * fy
+}
 
-    let band = data.rasterband(1)?;
+/// interpolates inside the 4x4 `window` using catmull-rom splines,
+/// where `fx` and `fy` go from 0 to 1 between the two center pixels
+fn cubic(window: [f64; 16], fx: f64, fy: f64) -> f64 {
+    let fx = fx.clamp(0.0, 1.0);
+    let fy = fy.clamp(0.0, 1.0);
 
-    let point_array = band.read_as_array::<i32>(
-        (x as isize, y as isize),
-        (1, 1),
-        (1, 1),
-        Some(ResampleAlg::NearestNeighbour),
-    )?;
+    let spline = |p: [f64; 4], t: f64| {
+        p[1] + 0.5
+            * t
+            * (p[2] - p[0]
+                + t * (2.0 * p[0] - 5.0 * p[1] + 4.0 * p[2] - p[3]
+                    + t * (3.0 * (p[1] - p[2]) + p[3] - p[0])))
+    };
 
-    Ok(point_array.into_raw_vec()[0])
+    let rows = [0, 1, 2, 3].map(|row| {
+        let i = row * 4;
+        spline([window[i], window[i + 1], window[i + 2], window[i + 3]], fx)
+    });
+    spline(rows, fy)
 }
 
 #[derive(Error, Debug)]
@@ -146,4 +626,33 @@ pub enum ElevationError {
     Gdal(#[from] gdal::errors::GdalError),
     #[error("coordinate not found")]
     CoordNotFound,
+    #[error("invalid elevation index")]
+    Index(#[from] serde_json::Error),
+    #[error("{0}")]
+    Arced(#[from] Arc<Self>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bilinear_interpolates_between_pixels() {
+        let window = [0.0, 10.0, 20.0, 30.0];
+
+        assert_eq!(bilinear(window, 0.0, 0.0), 0.0);
+        assert_eq!(bilinear(window, 1.0, 1.0), 30.0);
+        assert_eq!(bilinear(window, 0.5, 0.5), 15.0);
+        assert_eq!(bilinear(window, 0.5, 0.0), 5.0);
+    }
+
+    #[test]
+    fn cubic_matches_pixel_centers_and_planes() {
+        // a plane, which cubic interpolation has to reproduce exactly
+        let window: [f64; 16] = std::array::from_fn(|i| (i % 4) as f64 * 10.0 + (i / 4) as f64);
+
+        assert_eq!(cubic(window, 0.0, 0.0), window[5]);
+        assert_eq!(cubic(window, 1.0, 1.0), window[10]);
+        assert!((cubic(window, 0.25, 0.5) - 14.0).abs() < 1e-9);
+    }
 }
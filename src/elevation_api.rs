@@ -0,0 +1,332 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use geo::{GeodesicDistance, Point};
+use geojson::{GeoJson, Value};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    app_state::AppState,
+    elevation::{ElevationMap, Interpolation},
+    graph::utils::round,
+};
+
+/// Max amount of coordinates looked up in a single request, including the samples of a line
+const MAX_POINTS: usize = 10_000;
+/// Default distance between the samples of a line profile, in meters. Around the resolution of SRTM
+const DEFAULT_SPACING: f64 = 30.0;
+
+#[derive(Error, Debug)]
+pub enum ElevationApiError {
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("elevation task failed")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+impl IntoResponse for ElevationApiError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Join(_) => {
+                tracing::error!("elevation lookup failed: {self:?}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        };
+        (status, format!("{self}")).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LookupParams {
+    interpolation: Option<Interpolation>,
+    /// distance between the samples of a line profile, in meters
+    spacing: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct GetParams {
+    lng: Option<f64>,
+    lat: Option<f64>,
+    /// batch of coordinates, as `lng,lat;lng,lat;...`
+    points: Option<String>,
+    // not flattened from LookupParams, since flattening breaks parsing numbers from query strings
+    interpolation: Option<Interpolation>,
+    spacing: Option<f64>,
+}
+
+/// What to look up, in the body of a POST request
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ElevationQuery {
+    /// a single `[lng, lat]` coordinate
+    Point(Vec<f64>),
+    /// a batch of `[lng, lat]` coordinates
+    Points(Vec<Vec<f64>>),
+    /// a LineString geometry or feature, looked up as a sampled profile
+    Line(GeoJson),
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ElevationResponse {
+    Point {
+        elevation: Option<f64>,
+    },
+    Points {
+        elevations: Vec<Option<f64>>,
+    },
+    Line {
+        /// length of the line, in meters
+        length: f64,
+        profile: Vec<ProfileSample>,
+    },
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ProfileSample {
+    lng: f64,
+    lat: f64,
+    /// distance from the start of the line, in meters
+    distance: f64,
+    elevation: Option<f64>,
+}
+
+/// `GET /elevation?lng=..&lat=..` or `GET /elevation?points=lng,lat;lng,lat`
+pub async fn get_elevation(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<GetParams>,
+) -> Result<Json<ElevationResponse>, ElevationApiError> {
+    let query = match (params.lng, params.lat, params.points) {
+        (Some(lng), Some(lat), None) => ElevationQuery::Point(vec![lng, lat]),
+        (None, None, Some(points)) => ElevationQuery::Points(parse_points(&points)?),
+        _ => Err(ElevationApiError::BadRequest(
+            "expected either lng and lat, or points".to_string(),
+        ))?,
+    };
+
+    let params = LookupParams {
+        interpolation: params.interpolation,
+        spacing: params.spacing,
+    };
+    lookup(state.elevation_map.clone(), query, params).await
+}
+
+/// `POST /elevation` with a coordinate, a list of coordinates or a GeoJSON LineString as the body
+pub async fn post_elevation(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LookupParams>,
+    Json(query): Json<ElevationQuery>,
+) -> Result<Json<ElevationResponse>, ElevationApiError> {
+    lookup(state.elevation_map.clone(), query, params).await
+}
+
+async fn lookup(
+    elevation_map: Arc<ElevationMap>,
+    query: ElevationQuery,
+    params: LookupParams,
+) -> Result<Json<ElevationResponse>, ElevationApiError> {
+    let interpolation = params.interpolation.unwrap_or_default();
+    let query = validate(query, params.spacing)?;
+
+    // lookups do blocking io, so we keep them out of the async runtime
+    let response = tokio::task::spawn_blocking(move || {
+        let mut map = elevation_map.cached_with(interpolation);
+        let mut lookup = |c: &[f64]| map.lookup(c[0], c[1]).map(round);
+
+        match query {
+            Validated::Point(c) => ElevationResponse::Point {
+                elevation: lookup(&c),
+            },
+            Validated::Points(cs) => ElevationResponse::Points {
+                elevations: cs.iter().map(|c| lookup(c)).collect(),
+            },
+            Validated::Line(line, spacing) => {
+                let (length, samples) = sample_line(&line, spacing);
+                let profile = samples
+                    .into_iter()
+                    .map(|(c, distance)| ProfileSample {
+                        lng: c[0],
+                        lat: c[1],
+                        distance: round(distance),
+                        elevation: lookup(&c),
+                    })
+                    .collect();
+                ElevationResponse::Line {
+                    length: round(length),
+                    profile,
+                }
+            }
+        }
+    })
+    .await?;
+
+    Ok(Json(response))
+}
+
+enum Validated {
+    Point(Vec<f64>),
+    Points(Vec<Vec<f64>>),
+    /// with the spacing between samples
+    Line(Vec<Vec<f64>>, f64),
+}
+
+/// `spacing` is only checked for lines, since it isn't used for points
+fn validate(query: ElevationQuery, spacing: Option<f64>) -> Result<Validated, ElevationApiError> {
+    let bad_request = |message: &str| Err(ElevationApiError::BadRequest(message.to_string()));
+
+    let is_coord = |c: &Vec<f64>| {
+        c.len() >= 2 && (-180.0..=180.0).contains(&c[0]) && (-90.0..=90.0).contains(&c[1])
+    };
+
+    match query {
+        ElevationQuery::Point(c) if is_coord(&c) => Ok(Validated::Point(c)),
+        ElevationQuery::Point(_) => bad_request("invalid coordinate"),
+        ElevationQuery::Points(cs) if cs.len() > MAX_POINTS => bad_request(&format!(
+            "at most {MAX_POINTS} coordinates can be looked up at once"
+        )),
+        ElevationQuery::Points(cs) if cs.iter().all(is_coord) => Ok(Validated::Points(cs)),
+        ElevationQuery::Points(_) => bad_request("invalid coordinates"),
+        ElevationQuery::Line(geojson) => {
+            let spacing = spacing.unwrap_or(DEFAULT_SPACING);
+            if spacing.is_nan() || spacing <= 0.0 {
+                return bad_request("spacing has to be positive");
+            }
+
+            let geometry = match geojson {
+                GeoJson::Geometry(geometry) => Some(geometry),
+                GeoJson::Feature(feature) => feature.geometry,
+                GeoJson::FeatureCollection(_) => None,
+            };
+            match geometry.map(|g| g.value) {
+                Some(Value::LineString(line)) if line.len() > MAX_POINTS / 2 => bad_request(
+                    &format!("lines can have at most {} coordinates", MAX_POINTS / 2),
+                ),
+                Some(Value::LineString(line)) if line.len() >= 2 && line.iter().all(is_coord) => {
+                    Ok(Validated::Line(line, spacing))
+                }
+                _ => bad_request("expected a LineString with at least two valid coordinates"),
+            }
+        }
+    }
+}
+
+fn parse_points(points: &str) -> Result<Vec<Vec<f64>>, ElevationApiError> {
+    points
+        .split(';')
+        .map(|point| {
+            point
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| ElevationApiError::BadRequest(format!("invalid coordinate {point}")))
+        })
+        .collect()
+}
+
+/// Samples points every `spacing` meters along the line, always including its vertices.
+/// Returns the length of the line, and the samples with their distance from the start
+///
+/// the spacing is increased if needed, so there are at most around [`MAX_POINTS`] samples
+fn sample_line(line: &[Vec<f64>], spacing: f64) -> (f64, Vec<(Vec<f64>, f64)>) {
+    let point = |c: &[f64]| Point::new(c[0], c[1]);
+    let lengths = line
+        .windows(2)
+        .map(|pair| point(&pair[0]).geodesic_distance(&point(&pair[1])))
+        .collect::<Vec<_>>();
+    let length = lengths.iter().sum::<f64>();
+
+    let spacing = spacing.max(length / MAX_POINTS.saturating_sub(line.len()).max(1) as f64);
+
+    let mut samples = vec![(line[0][..2].to_vec(), 0.0)];
+    let mut distance = 0.0;
+    for (pair, segment) in line.windows(2).zip(lengths) {
+        let (from, to) = (&pair[0], &pair[1]);
+
+        // intermediate samples are interpolated linearly, segments are short enough for it not to matter
+        let steps = (segment / spacing).ceil() as usize;
+        for step in 1..steps {
+            let t = step as f64 / steps as f64;
+            samples.push((
+                vec![
+                    from[0] + (to[0] - from[0]) * t,
+                    from[1] + (to[1] - from[1]) * t,
+                ],
+                distance + segment * t,
+            ));
+        }
+
+        distance += segment;
+        samples.push((to[..2].to_vec(), distance));
+    }
+
+    (length, samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_line() {
+        // one degree of latitude is around 111km, so this is around 1110m long
+        let line = vec![vec![0.0, 0.0], vec![0.0, 0.01]];
+        let (length, samples) = sample_line(&line, 100.0);
+
+        assert!((length - 1105.7).abs() < 1.0);
+        assert_eq!(samples.len(), 13);
+        assert_eq!(samples[0], (vec![0.0, 0.0], 0.0));
+        assert_eq!(samples[12], (vec![0.0, 0.01], length));
+        assert!(samples
+            .windows(2)
+            .all(|pair| pair[1].1 - pair[0].1 <= 100.0));
+    }
+
+    #[test]
+    fn test_validate_spacing() {
+        let point = || ElevationQuery::Point(vec![2.0, 41.0]);
+        let line = || {
+            ElevationQuery::Line(GeoJson::Geometry(
+                Value::LineString(vec![vec![2.0, 41.0], vec![3.0, 42.0]]).into(),
+            ))
+        };
+
+        assert!(matches!(
+            validate(point(), Some(-1.0)),
+            Ok(Validated::Point(_))
+        ));
+        assert!(validate(line(), Some(-1.0)).is_err());
+        assert!(validate(line(), Some(f64::NAN)).is_err());
+        assert!(matches!(
+            validate(line(), None),
+            Ok(Validated::Line(_, spacing)) if spacing == DEFAULT_SPACING
+        ));
+    }
+
+    #[test]
+    fn test_untagged_query() {
+        let query = |json| serde_json::from_value::<ElevationQuery>(json).unwrap();
+
+        assert!(matches!(
+            query(serde_json::json!([2.0, 41.0])),
+            ElevationQuery::Point(_)
+        ));
+        assert!(matches!(
+            query(serde_json::json!([[2.0, 41.0], [3.0, 42.0]])),
+            ElevationQuery::Points(_)
+        ));
+        assert!(matches!(
+            query(serde_json::json!({
+                "type": "LineString",
+                "coordinates": [[2.0, 41.0], [3.0, 42.0]],
+            })),
+            ElevationQuery::Line(_)
+        ));
+    }
+}
@@ -0,0 +1,608 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Write,
+};
+
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use gdal::{
+    spatial_ref::SpatialRef,
+    vector::{FieldValue, Geometry, LayerAccess, OGRFieldType, OGRwkbGeometryType},
+    DriverManager, LayerOptions,
+};
+use geo::Centroid;
+use geojson::{Feature, FeatureCollection, JsonValue, Value};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Property the frontend uses to store which layer a feature belongs to
+const LAYER_PROPERTY: &str = "__layer";
+/// Name used for features that don't belong to any layer
+const DEFAULT_LAYER: &str = "results";
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Gpx,
+    Kml,
+    Csv,
+    Gpkg,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Gpx => "application/gpx+xml",
+            Self::Kml => "application/vnd.google-earth.kml+xml",
+            Self::Csv => "text/csv",
+            Self::Gpkg => "application/geopackage+sqlite3",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Gpx => "gpx",
+            Self::Kml => "kml",
+            Self::Csv => "csv",
+            Self::Gpkg => "gpkg",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("gdal error")]
+    Gdal(#[from] gdal::errors::GdalError),
+    #[error("error serializing geometry")]
+    Json(#[from] serde_json::Error),
+    #[error("error writing export")]
+    Fmt(#[from] std::fmt::Error),
+    #[error("export task failed")]
+    Join(#[from] tokio::task::JoinError),
+}
+
+impl IntoResponse for ExportError {
+    fn into_response(self) -> Response {
+        tracing::error!("export failed: {self:?}");
+        (StatusCode::INTERNAL_SERVER_ERROR, format!("{self}")).into_response()
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    format: ExportFormat,
+}
+
+/// Converts the FeatureCollection in the body into `format`, returning it as a download
+pub async fn export_results(
+    Query(params): Query<ExportParams>,
+    Json(collection): Json<FeatureCollection>,
+) -> Result<impl IntoResponse, ExportError> {
+    let format = params.format;
+    // gdal is blocking, so we keep it out of the async runtime
+    let bytes = tokio::task::spawn_blocking(move || export(collection, format)).await??;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export.{}\"", format.extension()),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+/// Serializes `collection` into `format`
+///
+/// Features are grouped by the layer they were shown in, which becomes a folder in KML,
+/// a layer in GeoPackage, and a column in CSV
+pub fn export(collection: FeatureCollection, format: ExportFormat) -> Result<Vec<u8>, ExportError> {
+    let layers = group_layers(collection);
+
+    match format {
+        ExportFormat::Gpx => Ok(to_gpx(&layers)?.into_bytes()),
+        ExportFormat::Kml => Ok(to_kml(&layers)?.into_bytes()),
+        ExportFormat::Csv => Ok(to_csv(&layers).into_bytes()),
+        ExportFormat::Gpkg => to_gpkg(&layers),
+    }
+}
+
+/// Groups the features by their layer, keeping the order in which layers first appear
+fn group_layers(collection: FeatureCollection) -> Vec<(String, Vec<Feature>)> {
+    let mut layers: Vec<(String, Vec<Feature>)> = vec![];
+
+    for feature in collection.features {
+        let name = feature
+            .property(LAYER_PROPERTY)
+            .and_then(JsonValue::as_str)
+            .unwrap_or(DEFAULT_LAYER)
+            .to_string();
+
+        match layers.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, features)) => features.push(feature),
+            None => layers.push((name, vec![feature])),
+        }
+    }
+
+    layers
+}
+
+/// Returns the tags of the feature as strings, skipping the internal `__` properties
+fn tags(feature: &Feature) -> Vec<(&str, String)> {
+    let Some(properties) = &feature.properties else {
+        return vec![];
+    };
+
+    properties
+        .iter()
+        .filter(|(k, _)| !k.starts_with("__"))
+        .map(|(k, v)| (k.as_str(), value_to_string(v)))
+        .collect()
+}
+
+fn value_to_string(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+/// `name` tag if present, `osm_type/osm_id` otherwise
+fn feature_name(feature: &Feature) -> String {
+    let property = |k: &str| feature.property(k).map(value_to_string);
+
+    property("name").unwrap_or_else(|| {
+        format!(
+            "{}/{}",
+            property("osm_type").unwrap_or_default(),
+            property("osm_id").unwrap_or_default()
+        )
+    })
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Splits a geometry into the points and lines that GPX can represent.
+/// Polygon rings become lines
+fn gpx_parts<'a>(value: &'a Value, points: &mut Vec<&'a [f64]>, lines: &mut Vec<&'a [Vec<f64>]>) {
+    match value {
+        Value::Point(p) => points.push(p),
+        Value::MultiPoint(ps) => points.extend(ps.iter().map(Vec::as_slice)),
+        Value::LineString(l) => lines.push(l),
+        Value::MultiLineString(ls) | Value::Polygon(ls) => {
+            lines.extend(ls.iter().map(Vec::as_slice))
+        }
+        Value::MultiPolygon(ps) => lines.extend(ps.iter().flatten().map(Vec::as_slice)),
+        Value::GeometryCollection(gs) => {
+            for g in gs {
+                gpx_parts(&g.value, points, lines);
+            }
+        }
+    }
+}
+
+/// Points become waypoints, and everything else becomes a track with one segment per line
+fn to_gpx(layers: &[(String, Vec<Feature>)]) -> Result<String, ExportError> {
+    let mut waypoints = String::new();
+    let mut tracks = String::new();
+
+    for (layer, features) in layers {
+        for feature in features {
+            let Some(geometry) = &feature.geometry else {
+                continue;
+            };
+
+            let mut points = vec![];
+            let mut lines = vec![];
+            gpx_parts(&geometry.value, &mut points, &mut lines);
+
+            let name = escape_xml(&feature_name(feature));
+            let layer = escape_xml(layer);
+            let desc = escape_xml(
+                &tags(feature)
+                    .iter()
+                    .map(|(k, v)| format!("{k}={v}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+            let extensions = tags(feature)
+                .iter()
+                .filter(|(k, _)| *k == "osm_id" || *k == "osm_type")
+                .map(|(k, v)| format!("<underpass:{k}>{}</underpass:{k}>", escape_xml(v)))
+                .collect::<String>();
+
+            for point in points {
+                writeln!(
+                    waypoints,
+                    r#"<wpt lat="{}" lon="{}"><name>{name}</name><desc>{desc}</desc><type>{layer}</type><extensions>{extensions}</extensions></wpt>"#,
+                    point[1], point[0]
+                )?;
+            }
+
+            if lines.is_empty() {
+                continue;
+            }
+
+            write!(
+                tracks,
+                "<trk><name>{name}</name><desc>{desc}</desc><type>{layer}</type><extensions>{extensions}</extensions>"
+            )?;
+            for line in lines {
+                tracks.push_str("<trkseg>");
+                for point in line {
+                    write!(tracks, r#"<trkpt lat="{}" lon="{}"/>"#, point[1], point[0])?;
+                }
+                tracks.push_str("</trkseg>");
+            }
+            tracks.push_str("</trk>\n");
+        }
+    }
+
+    // waypoints have to go before tracks
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="Underpass" xmlns="http://www.topografix.com/GPX/1/1" xmlns:underpass="https://underpass.versary.town/gpx">
+{waypoints}{tracks}</gpx>
+"#
+    ))
+}
+
+fn kml_coordinates(positions: &[Vec<f64>]) -> String {
+    positions
+        .iter()
+        .map(|p| format!("{},{}", p[0], p[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn kml_polygon(rings: &[Vec<Vec<f64>>]) -> String {
+    let mut polygon = String::from("<Polygon>");
+    for (i, ring) in rings.iter().enumerate() {
+        let boundary = if i == 0 {
+            "outerBoundaryIs"
+        } else {
+            "innerBoundaryIs"
+        };
+        polygon.push_str(&format!(
+            "<{boundary}><LinearRing><coordinates>{}</coordinates></LinearRing></{boundary}>",
+            kml_coordinates(ring)
+        ));
+    }
+    polygon.push_str("</Polygon>");
+    polygon
+}
+
+fn kml_geometry(value: &Value) -> String {
+    let multi = |parts: Vec<String>| format!("<MultiGeometry>{}</MultiGeometry>", parts.concat());
+
+    match value {
+        Value::Point(p) => format!(
+            "<Point><coordinates>{},{}</coordinates></Point>",
+            p[0], p[1]
+        ),
+        Value::MultiPoint(ps) => multi(
+            ps.iter()
+                .map(|p| kml_geometry(&Value::Point(p.clone())))
+                .collect(),
+        ),
+        Value::LineString(l) => format!(
+            "<LineString><coordinates>{}</coordinates></LineString>",
+            kml_coordinates(l)
+        ),
+        Value::MultiLineString(ls) => multi(
+            ls.iter()
+                .map(|l| kml_geometry(&Value::LineString(l.clone())))
+                .collect(),
+        ),
+        Value::Polygon(rings) => kml_polygon(rings),
+        Value::MultiPolygon(ps) => multi(ps.iter().map(|p| kml_polygon(p)).collect::<Vec<_>>()),
+        Value::GeometryCollection(gs) => multi(gs.iter().map(|g| kml_geometry(&g.value)).collect()),
+    }
+}
+
+/// Every layer becomes a folder, and the tags are stored as `ExtendedData`
+fn to_kml(layers: &[(String, Vec<Feature>)]) -> Result<String, ExportError> {
+    let mut folders = String::new();
+
+    for (layer, features) in layers {
+        writeln!(folders, "<Folder><name>{}</name>", escape_xml(layer))?;
+
+        for feature in features {
+            let Some(geometry) = &feature.geometry else {
+                continue;
+            };
+
+            let data = tags(feature)
+                .iter()
+                .map(|(k, v)| {
+                    format!(
+                        r#"<Data name="{}"><value>{}</value></Data>"#,
+                        escape_xml(k),
+                        escape_xml(v)
+                    )
+                })
+                .collect::<String>();
+
+            writeln!(
+                folders,
+                "<Placemark><name>{}</name><ExtendedData>{data}</ExtendedData>{}</Placemark>",
+                escape_xml(&feature_name(feature)),
+                kml_geometry(&geometry.value)
+            )?;
+        }
+
+        folders.push_str("</Folder>\n");
+    }
+
+    Ok(format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<kml xmlns="http://www.opengis.net/kml/2.2">
+<Document>
+{folders}</Document>
+</kml>
+"#
+    ))
+}
+
+fn escape_csv(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// One row per feature, with the position of the feature and a column for every tag.
+/// Lines and polygons use their centroid as the position
+fn to_csv(layers: &[(String, Vec<Feature>)]) -> String {
+    // osm_id and osm_type always go first
+    let keys = layers
+        .iter()
+        .flat_map(|(_, features)| features)
+        .flat_map(|f| tags(f).into_iter().map(|(k, _)| k))
+        .filter(|k| *k != "osm_id" && *k != "osm_type")
+        .collect::<BTreeSet<_>>();
+
+    let mut csv = ["layer", "osm_type", "osm_id", "lat", "lon"]
+        .into_iter()
+        .chain(keys.iter().copied())
+        .map(escape_csv)
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+
+    for (layer, features) in layers {
+        for feature in features {
+            let tags = tags(feature);
+            let tag = |key: &str| {
+                tags.iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.as_str())
+                    .unwrap_or_default()
+            };
+
+            let centroid = feature
+                .geometry
+                .as_ref()
+                .and_then(|g| geo::Geometry::<f64>::try_from(&g.value).ok())
+                .and_then(|g| g.centroid());
+            let (lat, lon) = centroid
+                .map(|c| (c.y().to_string(), c.x().to_string()))
+                .unwrap_or_default();
+
+            let row = [
+                layer.as_str(),
+                tag("osm_type"),
+                tag("osm_id"),
+                lat.as_str(),
+                lon.as_str(),
+            ]
+            .into_iter()
+            .chain(keys.iter().map(|k| tag(k)))
+            .map(escape_csv)
+            .collect::<Vec<_>>()
+            .join(",");
+            csv.push_str(&row);
+            csv.push('\n');
+        }
+    }
+
+    csv
+}
+
+/// Field names for the tags of a GeoPackage layer. Column names are case insensitive, and `fid` and
+/// `geom` are taken by the feature id and the geometry, so clashing keys get a suffix
+fn gpkg_field_names<'a>(keys: impl Iterator<Item = &'a str>) -> HashMap<&'a str, String> {
+    let mut used = HashSet::from(["fid".to_string(), "geom".to_string()]);
+    keys.map(|key| {
+        let mut name = key.to_string();
+        let mut suffix = 1;
+        while !used.insert(name.to_lowercase()) {
+            name = format!("{key}_{suffix}");
+            suffix += 1;
+        }
+        (key, name)
+    })
+    .collect()
+}
+
+/// Writes every layer to a GeoPackage layer, with a text field for every tag
+fn to_gpkg(layers: &[(String, Vec<Feature>)]) -> Result<Vec<u8>, ExportError> {
+    let path = format!("/vsimem/underpass-export-{}.gpkg", rand::random::<u64>());
+
+    let driver = DriverManager::get_driver_by_name("GPKG")?;
+    let mut dataset = driver.create_vector_only(&path)?;
+    let srs = SpatialRef::from_epsg(4326)?;
+
+    for (name, features) in layers {
+        let mut layer = dataset.create_layer(LayerOptions {
+            name,
+            srs: Some(&srs),
+            ty: OGRwkbGeometryType::wkbUnknown,
+            ..Default::default()
+        })?;
+
+        let keys = features
+            .iter()
+            .flat_map(|f| tags(f).into_iter().map(|(k, _)| k))
+            .filter(|k| *k != "osm_id")
+            .collect::<BTreeSet<_>>();
+        // osm_id goes first, so it keeps its name
+        let field_names = gpkg_field_names(std::iter::once("osm_id").chain(keys.iter().copied()));
+
+        let mut fields = vec![(field_names["osm_id"].as_str(), OGRFieldType::OFTInteger64)];
+        fields.extend(
+            keys.iter()
+                .map(|k| (field_names[k].as_str(), OGRFieldType::OFTString)),
+        );
+        layer.create_defn_fields(&fields)?;
+
+        for feature in features {
+            let Some(geometry) = &feature.geometry else {
+                continue;
+            };
+            let geometry = Geometry::from_geojson(&serde_json::to_string(geometry)?)?;
+
+            let (names, values): (Vec<_>, Vec<_>) = tags(feature)
+                .into_iter()
+                .filter_map(|(k, v)| {
+                    let value = if k == "osm_id" {
+                        FieldValue::Integer64Value(v.parse().ok()?)
+                    } else {
+                        FieldValue::StringValue(v)
+                    };
+                    Some((field_names[k].as_str(), value))
+                })
+                .unzip();
+
+            layer.create_feature_fields(geometry, &names, &values)?;
+        }
+    }
+
+    // the file is only complete once the dataset is closed
+    drop(dataset);
+
+    Ok(gdal::vsi::get_vsi_mem_file_bytes_owned(&path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collection() -> FeatureCollection {
+        serde_json::from_value(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [2.0, 41.0] },
+                    "properties": {
+                        "osm_id": 1,
+                        "osm_type": "node",
+                        "name": "a, \"b\"",
+                        "__layer": "Peaks",
+                    },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[2.0, 41.0], [3.0, 42.0]] },
+                    "properties": {
+                        "osm_id": 2,
+                        "osm_type": "way",
+                        "highway": "track",
+                        "__children_ids": [3, 4],
+                    },
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn csv_flattens_tags() {
+        let csv = String::from_utf8(export(collection(), ExportFormat::Csv).unwrap()).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "layer,osm_type,osm_id,lat,lon,highway,name");
+        assert_eq!(lines[1], r#"Peaks,node,1,41,2,,"a, ""b""""#);
+        assert_eq!(lines[2], "results,way,2,41.5,2.5,track,");
+    }
+
+    #[test]
+    fn gpx_waypoints_and_tracks() {
+        let gpx = String::from_utf8(export(collection(), ExportFormat::Gpx).unwrap()).unwrap();
+
+        assert!(gpx.contains(r#"<wpt lat="41" lon="2"><name>a, &quot;b&quot;</name>"#));
+        assert!(gpx.contains("<underpass:osm_type>way</underpass:osm_type>"));
+        assert!(
+            gpx.contains(r#"<trkseg><trkpt lat="41" lon="2"/><trkpt lat="42" lon="3"/></trkseg>"#)
+        );
+        assert!(gpx.find("<wpt").unwrap() < gpx.find("<trk>").unwrap());
+    }
+
+    #[test]
+    fn gpkg_round_trip() {
+        let mut collection = collection();
+        // keys that clash with another one when ignoring case, and with the feature id
+        collection.features.push(
+            serde_json::from_value(serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [2.5, 41.5] },
+                "properties": {
+                    "osm_id": 3,
+                    "osm_type": "node",
+                    "NAME": "upper",
+                    "fid": "tag",
+                    "__layer": "Peaks",
+                },
+            }))
+            .unwrap(),
+        );
+
+        let bytes = export(collection, ExportFormat::Gpkg).unwrap();
+        let path = format!("/vsimem/underpass-test-{}.gpkg", rand::random::<u64>());
+        gdal::vsi::create_mem_file(&path, bytes).unwrap();
+        {
+            let dataset = gdal::Dataset::open(&path).unwrap();
+
+            let mut peaks = dataset.layer_by_name("Peaks").unwrap();
+            let fields = peaks.defn().fields().map(|f| f.name()).collect::<Vec<_>>();
+            assert_eq!(fields, ["osm_id", "NAME", "fid_1", "name_1", "osm_type"]);
+
+            let features = peaks.features().collect::<Vec<_>>();
+            assert_eq!(features.len(), 2);
+            let field = |i: usize, name: &str| features[i].field_as_string_by_name(name).unwrap();
+            assert_eq!(
+                features[0].field_as_integer64_by_name("osm_id").unwrap(),
+                Some(1)
+            );
+            assert_eq!(field(0, "name_1").as_deref(), Some("a, \"b\""));
+            assert_eq!(field(1, "NAME").as_deref(), Some("upper"));
+            assert_eq!(field(1, "fid_1").as_deref(), Some("tag"));
+
+            let mut results = dataset.layer_by_name("results").unwrap();
+            let track = results.features().next().unwrap();
+            assert_eq!(
+                track.field_as_string_by_name("highway").unwrap().as_deref(),
+                Some("track")
+            );
+            assert_eq!(
+                track.geometry().unwrap().wkt().unwrap(),
+                "LINESTRING (2 41,3 42)"
+            );
+        }
+        gdal::vsi::unlink_mem_file(&path).unwrap();
+    }
+}
@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use thiserror::Error;
 
-use crate::nominatim::NominatimError;
+use crate::{nominatim::NominatimError, overpass::OverpassError};
 
 #[derive(Error, Debug)]
 pub enum GraphError {
@@ -14,6 +14,8 @@ pub enum GraphError {
     MapMissing,
     #[error("Node has no input")]
     InputMissing { node_id: String },
+    #[error("Node has no output named {output}")]
+    OutputMissing { node_id: String, output: String },
     #[error("Oql syntax error")]
     OqlSyntax {
         node_id: String,
@@ -24,14 +26,32 @@ pub enum GraphError {
     RoadAngle { message: String, node_id: String },
     #[error("Road length: {message}")]
     RoadLength { message: String, node_id: String },
+    #[error("In view of: {message}")]
+    InViewOf { message: String, node_id: String },
+    #[error("Slope: {message}")]
+    Slope { message: String, node_id: String },
+    #[error("Tag filter: {message}")]
+    TagFilter { message: String, node_id: String },
+    #[error("Spatial filter: {message}")]
+    Spatial { message: String, node_id: String },
+    #[error("Cluster: {message}")]
+    Cluster { message: String, node_id: String },
+    #[error("Transform: {message}")]
+    Transform { message: String, node_id: String },
+    #[error("Area: {message}")]
+    Area { message: String, node_id: String },
+    #[error("Junctions: {message}")]
+    Junction { message: String, node_id: String },
     #[error("Node has wrong input type {got}, expected {expected}")]
     WrongInputType { got: String, expected: String },
-    #[error("Error parsing Overpass json")]
-    OverpassJsonError,
+    #[error("{0}")]
+    Overpass(#[from] OverpassError),
     #[error("network error")]
     Network(#[from] reqwest::Error),
     #[error("nominatim error {0}")]
     Nominatim(#[from] NominatimError),
+    #[error("task failed")]
+    Join(#[from] tokio::task::JoinError),
     #[error("{0}")]
     Arced(#[from] Arc<Self>),
 }
@@ -2,14 +2,14 @@ use serde::Deserialize;
 
 use self::{
     errors::GraphError,
-    nodes::{GraphNode, GraphNodeInternal},
+    nodes::{map::Map, GraphNode, GraphNodeInternal},
 };
 
 pub mod errors;
 mod nodes;
 mod output;
 pub mod process;
-mod utils;
+pub(crate) mod utils;
 
 #[derive(Deserialize, Debug)]
 pub struct Graph {
@@ -17,12 +17,20 @@ pub struct Graph {
     connections: Vec<GraphConnection>,
 }
 
-impl Graph {
-    fn map_node(&self) -> Result<&GraphNode, GraphError> {
-        self.nodes
-            .iter()
-            .find(|n| matches!(n.node, GraphNodeInternal::Map(_)))
-            .ok_or(GraphError::MapMissing)
+/// returns all the Map nodes, which are the outputs of the graph
+fn map_nodes(nodes: &[GraphNode]) -> Result<Vec<(&GraphNode, &Map)>, GraphError> {
+    let maps = nodes
+        .iter()
+        .filter_map(|n| match &n.node {
+            GraphNodeInternal::Map(map) => Some((n, map)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    if maps.is_empty() {
+        Err(GraphError::MapMissing)
+    } else {
+        Ok(maps)
     }
 }
 
@@ -39,7 +47,7 @@ pub struct GraphConnection {
     _id: String,
     source: String,
     #[serde(rename = "sourceOutput")]
-    _source_output: String,
+    source_output: String,
     target: String,
     #[serde(rename = "targetInput")]
     target_input: String,
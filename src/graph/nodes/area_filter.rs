@@ -0,0 +1,182 @@
+use geo::{orient::Direction, GeodesicArea, MultiPolygon, Orient, Polygon};
+use geojson::{Feature, FeatureCollection, Value};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor, utils::round,
+    Control,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct AreaFilter {
+    /// Which value `min` and `max` apply to
+    measure: Control<AreaMeasure>,
+    min: Control<f64>,
+    max: Control<f64>,
+    /// Whether to write `area`, `perimeter` and `compactness` into the properties of the features
+    write_properties: Control<bool>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AreaMeasure {
+    /// in square meters
+    Area,
+    /// in meters
+    Perimeter,
+    /// Polsby-Popper score, from 0 for a line to 1 for a circle
+    Compactness,
+}
+
+#[async_trait::async_trait]
+impl Node for AreaFilter {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        let res = filter(
+            collection,
+            self.measure.value,
+            self.min.value,
+            self.max.value,
+            self.write_properties.value,
+            node_id,
+        )?;
+        Ok(res.into())
+    }
+}
+
+fn filter(
+    collection: FeatureCollection,
+    measure: AreaMeasure,
+    min: f64,
+    max: f64,
+    write_properties: bool,
+    node_id: &str,
+) -> Result<FeatureCollection, GraphError> {
+    // TODO add client-side validation too
+
+    if min > max {
+        Err(GraphError::Area {
+            message: "The min flag has a greater value than the max flag".to_string(),
+            node_id: node_id.to_string(),
+        })?;
+    }
+
+    if min < 0.0 || max < 0.0 {
+        Err(GraphError::Area {
+            message: "Min and Max have to be positive values".to_string(),
+            node_id: node_id.to_string(),
+        })?;
+    }
+
+    let features = collection
+        .features
+        .into_iter()
+        .filter_map(|mut feature| {
+            let (area, perimeter) = measure_polygon(&feature)?;
+            let compactness = compactness(area, perimeter);
+
+            let value = match measure {
+                AreaMeasure::Area => area,
+                AreaMeasure::Perimeter => perimeter,
+                AreaMeasure::Compactness => compactness,
+            };
+            if value < min || max < value {
+                return None;
+            }
+
+            if write_properties {
+                feature.set_property("area", round(area));
+                feature.set_property("perimeter", round(perimeter));
+                feature.set_property("compactness", round(compactness));
+            }
+            Some(feature)
+        })
+        .collect();
+
+    Ok(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    })
+}
+
+/// geodesic area and perimeter of polygon features, in square meters and meters
+fn measure_polygon(feature: &Feature) -> Option<(f64, f64)> {
+    // osm ways can go either way, and clockwise exteriors would measure the rest of the globe
+    let polygons = match &feature.geometry.as_ref()?.value {
+        value @ Value::Polygon(_) => MultiPolygon::new(vec![Polygon::try_from(value).ok()?]),
+        value @ Value::MultiPolygon(_) => MultiPolygon::try_from(value).ok()?,
+        _ => return None,
+    }
+    .orient(Direction::Default);
+
+    Some((
+        polygons.geodesic_area_unsigned(),
+        polygons.geodesic_perimeter(),
+    ))
+}
+
+/// Polsby-Popper score, the area of the polygon relative to a circle with the same perimeter
+fn compactness(area: f64, perimeter: f64) -> f64 {
+    if perimeter == 0.0 {
+        return 0.0;
+    }
+    4.0 * std::f64::consts::PI * area / perimeter.powi(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geojson::JsonValue;
+
+    #[test]
+    fn test_compactness() {
+        // a square is less compact than a circle
+        let square = compactness(100.0, 40.0);
+        assert!((square - std::f64::consts::PI / 4.0).abs() < 1e-9);
+
+        let r: f64 = 10.0;
+        let circle = compactness(std::f64::consts::PI * r * r, 2.0 * std::f64::consts::PI * r);
+        assert!((circle - 1.0).abs() < 1e-9);
+
+        assert_eq!(compactness(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_measure_polygon() {
+        // a 0.001 degree square at the equator, around 111m x 111m, in both windings
+        let square = |coords: JsonValue| -> Feature {
+            serde_json::from_value(serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "Polygon", "coordinates": [coords] },
+                "properties": {},
+            }))
+            .unwrap()
+        };
+        let counter_clockwise = square(serde_json::json!([
+            [0.0, 0.0],
+            [0.001, 0.0],
+            [0.001, 0.001],
+            [0.0, 0.001],
+            [0.0, 0.0]
+        ]));
+        let clockwise = square(serde_json::json!([
+            [0.0, 0.0],
+            [0.0, 0.001],
+            [0.001, 0.001],
+            [0.001, 0.0],
+            [0.0, 0.0]
+        ]));
+
+        for feature in [counter_clockwise, clockwise] {
+            let (area, perimeter) = measure_polygon(&feature).unwrap();
+            assert!((area - 12_300.0).abs() < 100.0);
+            assert!((perimeter - 443.0).abs() < 2.0);
+        }
+    }
+}
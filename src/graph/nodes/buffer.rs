@@ -0,0 +1,169 @@
+use geo::{
+    BooleanOps, ConvexHull, Coord, GeodesicDestination, Geometry, LineString, MultiPoint,
+    MultiPolygon, Point, Polygon,
+};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor,
+    utils::map_geometries, Control,
+};
+
+/// Amount of points used to approximate a circle
+const CIRCLE_SEGMENTS: usize = 32;
+
+/// Grows every feature into a polygon covering everything within a distance of it
+#[derive(Deserialize, Debug)]
+pub struct Buffer {
+    /// in meters
+    distance: Control<f64>,
+}
+
+#[async_trait::async_trait]
+impl Node for Buffer {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        // TODO add client-side validation too
+
+        if self.distance.value <= 0.0 {
+            Err(GraphError::Transform {
+                message: "Distance has to be greater than 0".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        let distance = self.distance.value;
+
+        let res = tokio::task::spawn_blocking(move || {
+            map_geometries(collection, |g| {
+                let buffered = buffer(&g, distance);
+                match buffered.0.len() {
+                    0 => None,
+                    1 => buffered.0.into_iter().next().map(Into::into),
+                    _ => Some(buffered.into()),
+                }
+            })
+        })
+        .await?;
+        Ok(res.into())
+    }
+}
+
+/// geo doesn't implement buffers, so they are built joining a circle around every point,
+/// the convex hull of the circles at both ends of every segment, and the polygons themselves
+fn buffer(geometry: &Geometry, meters: f64) -> MultiPolygon {
+    let mut lines = vec![];
+    let mut polygons = vec![];
+    parts(geometry, &mut lines, &mut polygons);
+
+    for line in lines {
+        let circles = line
+            .0
+            .iter()
+            .map(|c| circle(Point::from(*c), meters))
+            .collect::<Vec<_>>();
+
+        if circles.len() == 1 {
+            polygons.extend(circles);
+            continue;
+        }
+
+        for pair in circles.windows(2) {
+            let coords = pair
+                .iter()
+                .flat_map(|c| c.exterior().0.iter().copied())
+                .map(Point::from)
+                .collect::<MultiPoint>();
+            polygons.push(coords.convex_hull());
+        }
+    }
+
+    union_all(
+        polygons
+            .into_iter()
+            .map(|p| MultiPolygon::new(vec![p]))
+            .collect(),
+    )
+}
+
+/// unions the polygons in pairs until one is left, so every union joins polygons of a similar size,
+/// instead of growing a single polygon one part at a time
+fn union_all(mut polygons: Vec<MultiPolygon>) -> MultiPolygon {
+    while polygons.len() > 1 {
+        let mut pairs = polygons.into_iter();
+        let mut joined = vec![];
+        while let Some(a) = pairs.next() {
+            joined.push(match pairs.next() {
+                Some(b) => a.union(&b),
+                None => a,
+            });
+        }
+        polygons = joined;
+    }
+    polygons.pop().unwrap_or_else(|| MultiPolygon::new(vec![]))
+}
+
+/// splits a geometry into the lines whose points and segments have to be buffered, and the polygons covered by the buffer
+fn parts(geometry: &Geometry, lines: &mut Vec<LineString>, polygons: &mut Vec<Polygon>) {
+    let mut add_polygon = |polygon: &Polygon, lines: &mut Vec<LineString>| {
+        lines.push(polygon.exterior().clone());
+        lines.extend(polygon.interiors().iter().cloned());
+        polygons.push(polygon.clone());
+    };
+
+    match geometry {
+        Geometry::Point(p) => lines.push(LineString::new(vec![p.0])),
+        Geometry::MultiPoint(ps) => lines.extend(ps.iter().map(|p| LineString::new(vec![p.0]))),
+        Geometry::Line(l) => lines.push(LineString::new(vec![l.start, l.end])),
+        Geometry::LineString(l) => lines.push(l.clone()),
+        Geometry::MultiLineString(ls) => lines.extend(ls.iter().cloned()),
+        Geometry::Polygon(p) => add_polygon(p, lines),
+        Geometry::MultiPolygon(ps) => ps.iter().for_each(|p| add_polygon(p, lines)),
+        Geometry::Rect(r) => add_polygon(&r.to_polygon(), lines),
+        Geometry::Triangle(t) => add_polygon(&t.to_polygon(), lines),
+        Geometry::GeometryCollection(gs) => {
+            for g in gs {
+                parts(g, lines, polygons);
+            }
+        }
+    }
+}
+
+/// geodesic circle of `meters` radius around `center`, counter-clockwise like geojson exteriors
+fn circle(center: Point, meters: f64) -> Polygon {
+    let mut ring = (0..CIRCLE_SEGMENTS)
+        .map(|i| {
+            let bearing = -(i as f64) * 360.0 / CIRCLE_SEGMENTS as f64;
+            Coord::from(center.geodesic_destination(bearing, meters))
+        })
+        .collect::<Vec<_>>();
+    ring.push(ring[0]);
+    Polygon::new(LineString::new(ring), vec![])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_string, point, GeodesicArea};
+
+    #[test]
+    fn test_buffer() {
+        // a circle of 100m has an area of around 31400m²
+        let circle = buffer(&point!(x: 2.0, y: 41.0).into(), 100.0);
+        assert_eq!(circle.0.len(), 1);
+        assert!((circle.geodesic_area_unsigned() - 31_400.0).abs() < 500.0);
+
+        // 0.01 degrees of latitude are around 1110m, which gives 1110m x 200m plus the rounded ends
+        let line = buffer(
+            &line_string![(x: 2.0, y: 41.0), (x: 2.0, y: 41.005), (x: 2.0, y: 41.01)].into(),
+            100.0,
+        );
+        assert_eq!(line.0.len(), 1);
+        assert!((line.geodesic_area_unsigned() - 253_400.0).abs() < 3000.0);
+    }
+}
@@ -0,0 +1,60 @@
+use geo::Centroid as _;
+use geojson::FeatureCollection;
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor,
+    utils::map_geometries,
+};
+
+/// Turns every feature into a point at its centroid
+#[derive(Deserialize, Debug)]
+pub struct Centroid;
+
+#[async_trait::async_trait]
+impl Node for Centroid {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        Ok(centroids(collection).into())
+    }
+}
+
+fn centroids(collection: FeatureCollection) -> FeatureCollection {
+    map_geometries(collection, |g| g.centroid().map(Into::into))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geojson::Value;
+
+    #[test]
+    fn test_centroids() {
+        let collection: FeatureCollection = serde_json::from_value(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [{
+                "type": "Feature",
+                "id": 7,
+                "geometry": { "type": "LineString", "coordinates": [[2.0, 41.0], [2.2, 41.2]] },
+                "properties": { "name": "Carrer Gran" },
+            }],
+        }))
+        .unwrap();
+
+        let res = centroids(collection);
+        let feature = &res.features[0];
+        assert_eq!(feature.id, Some(geojson::feature::Id::Number(7.into())));
+        assert_eq!(feature.property("name"), Some(&"Carrer Gran".into()));
+        match &feature.geometry.as_ref().unwrap().value {
+            Value::Point(p) => {
+                assert!((p[0] - 2.1).abs() < 1e-9 && (p[1] - 41.1).abs() < 1e-9)
+            }
+            value => panic!("expected a point, got {value:?}"),
+        }
+    }
+}
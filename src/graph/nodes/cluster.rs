@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, HashMap};
+
+use geo::{Centroid, ConvexHull, GeodesicDistance, MultiPoint, Point};
+use geojson::{feature::Id, Feature, FeatureCollection, JsonValue, Value};
+use rtree_rs::{RTree, Rect};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::{NodeOutputs, DEFAULT_OUTPUT},
+    process::NodeProcessor,
+    utils::{expand_rect, osm_identity, METERS_PER_DEGREE},
+    Control,
+};
+
+/// Property with the cluster of a feature when using [`ClusterOutput::Points`]
+const CLUSTER_PROPERTY: &str = "cluster_id";
+
+#[derive(Deserialize, Debug)]
+pub struct Cluster {
+    algorithm: Control<ClusterAlgorithm>,
+    /// in meters. max distance between neighbours with DBSCAN, size of the cells with a grid
+    distance: Control<f64>,
+    /// minimum amount of features in a cluster
+    min_points: Control<usize>,
+    output: Control<ClusterOutput>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterAlgorithm {
+    /// features with at least `min_points` neighbours within `distance`, and the features around them
+    Dbscan,
+    /// features in the same `distance` sized cell
+    Grid,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterOutput {
+    /// a point per cluster
+    Centroids,
+    /// the convex hull of every cluster
+    Hulls,
+    /// the clustered features, with their cluster in [`CLUSTER_PROPERTY`]
+    Points,
+}
+
+#[async_trait::async_trait]
+impl Node for Cluster {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        // TODO add client-side validation too
+
+        if self.distance.value <= 0.0 {
+            Err(GraphError::Cluster {
+                message: "Distance has to be greater than 0".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        if self.min_points.value == 0 {
+            Err(GraphError::Cluster {
+                message: "Min points has to be at least 1".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        let (algorithm, distance, min_points, output) = (
+            self.algorithm.value,
+            self.distance.value,
+            self.min_points.value,
+            self.output.value,
+        );
+
+        let (clustered, rejected) = tokio::task::spawn_blocking(move || {
+            cluster(collection, algorithm, distance, min_points, output)
+        })
+        .await?;
+
+        Ok(NodeOutputs::new()
+            .with(DEFAULT_OUTPUT, clustered)
+            .with("rejected", rejected))
+    }
+}
+
+/// returns the clusters, and the features that aren't in any cluster
+fn cluster(
+    collection: FeatureCollection,
+    algorithm: ClusterAlgorithm,
+    distance: f64,
+    min_points: usize,
+    output: ClusterOutput,
+) -> (FeatureCollection, FeatureCollection) {
+    let _span = tracing::trace_span!("cluster::cluster");
+    let _span = _span.enter();
+
+    // features without a geometry can't be clustered
+    let (features, mut rejected): (Vec<_>, Vec<_>) = collection
+        .features
+        .into_iter()
+        .partition(|f| position(f).is_some());
+    let points = features.iter().filter_map(position).collect::<Vec<_>>();
+
+    let labels = match algorithm {
+        ClusterAlgorithm::Dbscan => dbscan(&points, distance, min_points),
+        ClusterAlgorithm::Grid => grid(&points, distance, min_points),
+    };
+
+    // cluster id -> features in it
+    let mut clusters: BTreeMap<usize, Vec<(Feature, Point)>> = BTreeMap::new();
+    for ((feature, point), label) in features.into_iter().zip(points).zip(labels) {
+        match label {
+            Some(label) => clusters.entry(label).or_default().push((feature, point)),
+            None => rejected.push(feature),
+        }
+    }
+
+    let features = clusters
+        .into_values()
+        .enumerate()
+        .flat_map(|(cluster_id, members)| match output {
+            ClusterOutput::Points => members
+                .into_iter()
+                .map(|(mut feature, _)| {
+                    feature.set_property(CLUSTER_PROPERTY, cluster_id);
+                    feature
+                })
+                .collect(),
+            ClusterOutput::Centroids | ClusterOutput::Hulls => {
+                let member_ids = members
+                    .iter()
+                    .map(|(f, _)| match osm_identity(f) {
+                        Some(identity) => identity.into(),
+                        None => match &f.id {
+                            Some(Id::String(s)) => s.clone().into(),
+                            Some(Id::Number(n)) => n.clone().into(),
+                            None => JsonValue::Null,
+                        },
+                    })
+                    .collect::<Vec<JsonValue>>();
+                let multi_point =
+                    MultiPoint::from(members.iter().map(|(_, p)| *p).collect::<Vec<_>>());
+
+                let geometry = match output {
+                    ClusterOutput::Hulls => Value::from(&multi_point.convex_hull()),
+                    _ => match multi_point.centroid() {
+                        Some(centroid) => Value::from(&centroid),
+                        None => return vec![],
+                    },
+                };
+
+                let mut feature = Feature {
+                    geometry: Some(geometry.into()),
+                    ..Default::default()
+                };
+                feature.set_property(CLUSTER_PROPERTY, cluster_id);
+                feature.set_property("member_count", members.len());
+                feature.set_property("member_ids", member_ids);
+                vec![feature]
+            }
+        })
+        .collect();
+
+    (
+        FeatureCollection {
+            bbox: None,
+            features,
+            foreign_members: None,
+        },
+        FeatureCollection {
+            bbox: None,
+            features: rejected,
+            foreign_members: None,
+        },
+    )
+}
+
+/// returns the point used as the position of the feature
+fn position(feature: &Feature) -> Option<Point> {
+    let geometry = feature.geometry.as_ref()?;
+    let geometry: geo::Geometry = (&geometry.value).try_into().ok()?;
+    geometry.centroid()
+}
+
+/// DBSCAN over geodesic distances. Returns the cluster of every point, or `None` for noise
+fn dbscan(points: &[Point], distance: f64, min_points: usize) -> Vec<Option<usize>> {
+    let mut tree = RTree::new();
+    for (i, p) in points.iter().enumerate() {
+        tree.insert(Rect::new_point([p.x(), p.y()]), i);
+    }
+
+    // points within `distance` of the point at `i`, including itself
+    let neighbours = |i: usize| {
+        let p = points[i];
+        let rect = expand_rect(geo::Rect::new(p.0, p.0), distance);
+        tree.search(Rect::new(
+            [rect.min().x, rect.min().y],
+            [rect.max().x, rect.max().y],
+        ))
+        .map(|item| *item.data)
+        .filter(|j| p.geodesic_distance(&points[*j]) <= distance)
+        .collect::<Vec<_>>()
+    };
+
+    let mut labels = vec![None; points.len()];
+    let mut visited = vec![false; points.len()];
+    let mut next_cluster = 0;
+
+    for i in 0..points.len() {
+        if visited[i] {
+            continue;
+        }
+        visited[i] = true;
+
+        let mut queue = neighbours(i);
+        if queue.len() < min_points {
+            continue;
+        }
+
+        let cluster = next_cluster;
+        next_cluster += 1;
+        labels[i] = Some(cluster);
+
+        while let Some(j) = queue.pop() {
+            if labels[j].is_none() {
+                labels[j] = Some(cluster);
+            }
+            if visited[j] {
+                continue;
+            }
+            visited[j] = true;
+
+            // only core points expand the cluster
+            let next = neighbours(j);
+            if next.len() >= min_points {
+                queue.extend(next);
+            }
+        }
+    }
+
+    labels
+}
+
+/// Groups points by `size` meter cells, keeping the cells with at least `min_points`
+fn grid(points: &[Point], size: f64, min_points: usize) -> Vec<Option<usize>> {
+    let cell_degrees = size / METERS_PER_DEGREE;
+    let cell = |p: &Point| {
+        let row = (p.y() / cell_degrees).floor();
+        // cells are narrower in degrees of longitude further from the equator, so they stay square
+        let lat = ((row + 0.5) * cell_degrees).clamp(-89.0, 89.0);
+        let column = (p.x() * lat.to_radians().cos() / cell_degrees).floor();
+        (row as i64, column as i64)
+    };
+
+    let cells = points.iter().map(cell).collect::<Vec<_>>();
+    let mut counts: HashMap<(i64, i64), usize> = HashMap::new();
+    for c in &cells {
+        *counts.entry(*c).or_default() += 1;
+    }
+
+    let mut ids: HashMap<(i64, i64), usize> = HashMap::new();
+    cells
+        .into_iter()
+        .map(|c| {
+            if counts[&c] < min_points {
+                return None;
+            }
+            let next = ids.len();
+            Some(*ids.entry(c).or_insert(next))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dbscan() {
+        // 0.0001 degrees are around 11m
+        let points = [
+            Point::new(0.0, 0.0),
+            Point::new(0.0001, 0.0),
+            Point::new(0.0002, 0.0),
+            Point::new(1.0, 1.0),
+            Point::new(1.0001, 1.0),
+            Point::new(0.5, 0.5),
+        ];
+
+        let labels = dbscan(&points, 15.0, 2);
+        assert_eq!(
+            labels,
+            vec![Some(0), Some(0), Some(0), Some(1), Some(1), None]
+        );
+
+        let labels = dbscan(&points, 15.0, 3);
+        assert_eq!(labels, vec![Some(0), Some(0), Some(0), None, None, None]);
+    }
+
+    #[test]
+    fn test_grid() {
+        let points = [
+            Point::new(0.00001, 0.00001),
+            Point::new(0.00002, 0.00002),
+            Point::new(1.0, 1.0),
+        ];
+
+        assert_eq!(grid(&points, 100.0, 2), vec![Some(0), Some(0), None]);
+        assert_eq!(grid(&points, 100.0, 1), vec![Some(0), Some(0), Some(1)]);
+    }
+}
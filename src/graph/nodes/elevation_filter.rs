@@ -1,47 +1,86 @@
 use crate::{
-    elevation::ElevationMap,
+    elevation::{ElevationMap, Interpolation},
     graph::{
         errors::GraphError,
         nodes::Node,
-        output::NodeOutput,
+        output::NodeOutputs,
         process::NodeProcessor,
         utils::{new_id, RAF_NUMBER},
         Control,
     },
 };
+use geo::CoordsIter;
 use geojson::{Feature, FeatureCollection, Value};
 use serde::Deserialize;
 
+/// Property set on features without elevation data when using [`MissingElevation::Flag`]
+const MISSING_PROPERTY: &str = "elevation_missing";
+
 #[derive(Deserialize, Debug)]
 pub struct ElevationFilter {
-    min: Control<i32>,
-    max: Control<i32>,
+    min: Control<f64>,
+    max: Control<f64>,
+    /// What to do with features we have no elevation data for. Defaults to dropping them
+    missing: Option<Control<MissingElevation>>,
+    interpolation: Option<Control<Interpolation>>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingElevation {
+    #[default]
+    Drop,
+    Keep,
+    /// keep the feature, setting [`MISSING_PROPERTY`] on it
+    Flag,
+}
+
+impl MissingElevation {
+    fn apply(self, mut feature: Feature) -> Option<Feature> {
+        match self {
+            Self::Drop => None,
+            Self::Keep => Some(feature),
+            Self::Flag => {
+                feature.set_property(MISSING_PROPERTY, true);
+                Some(feature)
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl Node for ElevationFilter {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         let collection = processor.get_input(node_id, "in").await?.into_features()?;
 
-        let res = filter(
-            collection,
-            self.min.value,
-            self.max.value,
-            node_id,
-            processor.elevation_map,
-        )?;
+        let (min, max) = (self.min.value, self.max.value);
+        let missing = self.missing.as_ref().map(|m| m.value).unwrap_or_default();
+        let interpolation = self
+            .interpolation
+            .as_ref()
+            .map(|i| i.value)
+            .unwrap_or_default();
+        let id = node_id.to_string();
+
+        let res = processor
+            .with_elevation(move |map| {
+                filter(collection, min, max, missing, interpolation, &id, map)
+            })
+            .await??;
         Ok(res.into())
     }
 }
 
 fn filter(
     collection: FeatureCollection,
-    min: i32,
-    max: i32,
+    min: f64,
+    max: f64,
+    missing: MissingElevation,
+    interpolation: Interpolation,
     node_id: &str,
     map: &ElevationMap,
 ) -> Result<FeatureCollection, GraphError> {
@@ -50,7 +89,7 @@ fn filter(
 
     // TODO add client-side validation too
 
-    let mut map = map.cached();
+    let mut map = map.cached_with(interpolation);
 
     if min > max {
         Err(GraphError::RoadAngle {
@@ -59,6 +98,8 @@ fn filter(
         })?;
     }
 
+    let in_range = |elevation: f64| min <= elevation && elevation <= max;
+
     let features = collection
         .features
         .into_iter()
@@ -68,36 +109,54 @@ fn filter(
             };
 
             match &geo.value {
-                Value::Point(point) => {
-                    let elevation = map.lookup_or_0(point[0], point[1]);
-                    if min <= elevation && elevation <= max {
-                        return vec![feature];
-                    }
-                }
+                Value::Point(point) => match map.lookup(point[0], point[1]) {
+                    Some(elevation) if in_range(elevation) => return vec![feature],
+                    Some(_) => {}
+                    None => return missing.apply(feature).into_iter().collect(),
+                },
                 Value::LineString(line) => {
                     return line
                         .windows(2)
                         .flat_map(|pair| {
-                            let elevation1 = map.lookup_or_0(pair[0][0], pair[0][1]);
-                            let elevation2 = map.lookup_or_0(pair[1][0], pair[1][1]);
-                            if (min <= elevation1 && elevation1 <= max)
-                                || (min <= elevation2 && elevation2 <= max)
+                            let elevation1 = map.lookup(pair[0][0], pair[0][1]);
+                            let elevation2 = map.lookup(pair[1][0], pair[1][1]);
+
+                            let segment = Feature {
+                                id: feature.id.clone().and_then(|id| new_id(id, RAF_NUMBER)),
+                                geometry: Some(Value::LineString(pair.to_vec()).into()),
+                                properties: feature.properties.clone(),
+                                ..Default::default()
+                            };
+
+                            if elevation1.is_some_and(in_range) || elevation2.is_some_and(in_range)
                             {
-                                return Some(Feature {
-                                    id: feature.id.clone().and_then(|id| new_id(id, RAF_NUMBER)),
-                                    geometry: Some(Value::LineString(pair.to_vec()).into()),
-                                    properties: feature.properties.clone(),
-                                    ..Default::default()
-                                });
+                                return Some(segment);
+                            }
+                            if elevation1.is_none() && elevation2.is_none() {
+                                return missing.apply(segment);
                             }
 
                             None
                         })
                         .collect();
                 }
-                Value::GeometryCollection(_) => unimplemented!(),
-                // we dont actually have any other type
-                _ => unreachable!(),
+                // polygons and relations are kept whole if any of their nodes is in range
+                value => {
+                    let Ok(geometry) = geo::Geometry::<f64>::try_from(value) else {
+                        return vec![];
+                    };
+                    let elevations = geometry
+                        .coords_iter()
+                        .map(|c| map.lookup(c.x, c.y))
+                        .collect::<Vec<_>>();
+
+                    if elevations.iter().flatten().any(|e| in_range(*e)) {
+                        return vec![feature];
+                    }
+                    if elevations.iter().all(Option::is_none) {
+                        return missing.apply(feature).into_iter().collect();
+                    }
+                }
             }
 
             vec![]
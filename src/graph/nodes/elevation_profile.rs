@@ -0,0 +1,136 @@
+use crate::{
+    elevation::ElevationMap,
+    graph::{
+        errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor, utils::round,
+        Control,
+    },
+};
+use geojson::{FeatureCollection, Value};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct ElevationProfile {
+    /// Whether to add the elevation as the third value of every coordinate
+    three_d: Control<bool>,
+}
+
+#[async_trait::async_trait]
+impl Node for ElevationProfile {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        let three_d = self.three_d.value;
+
+        let res = processor
+            .with_elevation(move |map| enrich(collection, three_d, map))
+            .await?;
+        Ok(res.into())
+    }
+}
+
+/// Adds the following properties, in meters, using only the coordinates we have elevation data for:
+/// - points: `elevation`
+/// - lines: `elevation_min`, `elevation_max`, `ascent` and `descent`, following the line from start to end
+/// - everything else: `elevation_min` and `elevation_max`
+fn enrich(collection: FeatureCollection, three_d: bool, map: &ElevationMap) -> FeatureCollection {
+    let _span = tracing::trace_span!("elevation_profile::enrich");
+    let _span = _span.enter();
+
+    let mut map = map.cached();
+
+    let features = collection
+        .features
+        .into_iter()
+        .map(|mut feature| {
+            let Some(geometry) = &mut feature.geometry else {
+                return feature;
+            };
+
+            let mut elevations = vec![];
+            for_each_position(&mut geometry.value, &mut |position| {
+                let elevation = map.lookup(position[0], position[1]);
+                if let (true, Some(elevation)) = (three_d, elevation) {
+                    position.truncate(2);
+                    position.push(round(elevation));
+                }
+                elevations.push(elevation);
+            });
+            let is_point = matches!(geometry.value, Value::Point(_));
+            let is_line = matches!(geometry.value, Value::LineString(_));
+
+            let known = elevations.iter().flatten().copied().collect::<Vec<_>>();
+            if known.is_empty() {
+                return feature;
+            }
+
+            if is_point {
+                feature.set_property("elevation", round(known[0]));
+                return feature;
+            }
+
+            let min = known.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = known.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            feature.set_property("elevation_min", round(min));
+            feature.set_property("elevation_max", round(max));
+
+            if is_line {
+                let (ascent, descent) = ascent_descent(&known);
+                feature.set_property("ascent", round(ascent));
+                feature.set_property("descent", round(descent));
+            }
+
+            feature
+        })
+        .collect();
+
+    tracing::trace!("there were {} cached datasets", map.cache.len());
+
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+fn for_each_position(value: &mut Value, f: &mut impl FnMut(&mut Vec<f64>)) {
+    match value {
+        Value::Point(p) => f(p),
+        Value::MultiPoint(ps) | Value::LineString(ps) => ps.iter_mut().for_each(f),
+        Value::MultiLineString(ls) | Value::Polygon(ls) => ls.iter_mut().flatten().for_each(f),
+        Value::MultiPolygon(ps) => ps.iter_mut().flatten().flatten().for_each(f),
+        Value::GeometryCollection(gs) => {
+            for g in gs {
+                for_each_position(&mut g.value, f);
+            }
+        }
+    }
+}
+
+/// total meters climbed and descended going through `elevations` in order
+fn ascent_descent(elevations: &[f64]) -> (f64, f64) {
+    elevations
+        .windows(2)
+        .fold((0.0, 0.0), |(ascent, descent), pair| {
+            let diff = pair[1] - pair[0];
+            if diff > 0.0 {
+                (ascent + diff, descent)
+            } else {
+                (ascent, descent - diff)
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascent_descent() {
+        assert_eq!(ascent_descent(&[10.0, 15.0, 12.0, 20.0]), (13.0, 3.0));
+        assert_eq!(ascent_descent(&[10.0]), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,196 @@
+use geo::{ConcaveHull, ConvexHull, CoordsIter, Geometry, MultiPoint, Point, Polygon};
+use geojson::{Feature, FeatureCollection, Value};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor,
+    utils::map_geometries, Control,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct Hull {
+    kind: Control<HullKind>,
+    /// only used by concave hulls. lower values follow the points more closely
+    concavity: Control<f64>,
+    /// Whether to get the hull of every feature, instead of a single hull for all of them
+    per_feature: Control<bool>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HullKind {
+    Convex,
+    Concave,
+}
+
+#[async_trait::async_trait]
+impl Node for Hull {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        // TODO add client-side validation too
+
+        if self.kind.value == HullKind::Concave && self.concavity.value <= 0.0 {
+            Err(GraphError::Transform {
+                message: "Concavity has to be greater than 0".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        let (kind, concavity, per_feature) = (
+            self.kind.value,
+            self.concavity.value,
+            self.per_feature.value,
+        );
+
+        let res =
+            tokio::task::spawn_blocking(move || hull(collection, kind, concavity, per_feature))
+                .await?;
+        Ok(res.into())
+    }
+}
+
+fn hull(
+    collection: FeatureCollection,
+    kind: HullKind,
+    concavity: f64,
+    per_feature: bool,
+) -> FeatureCollection {
+    let hull = |points: MultiPoint| match kind {
+        HullKind::Convex => points.convex_hull(),
+        HullKind::Concave => points.concave_hull(concavity),
+    };
+
+    if per_feature {
+        return map_geometries(collection, |g| Some(hull(points(&g)).into()));
+    }
+
+    let geometries = collection
+        .features
+        .iter()
+        .filter_map(|f| Geometry::<f64>::try_from(&f.geometry.as_ref()?.value).ok())
+        .collect::<Vec<_>>();
+    if geometries.is_empty() {
+        return FeatureCollection {
+            bbox: None,
+            features: vec![],
+            foreign_members: None,
+        };
+    }
+
+    let all_points = Geometry::GeometryCollection(geometries.iter().cloned().collect());
+    let polygon: Polygon = hull(points(&all_points));
+
+    let mut feature = Feature {
+        geometry: Some(Value::from(&polygon).into()),
+        ..Default::default()
+    };
+    feature.set_property("member_count", geometries.len());
+
+    FeatureCollection {
+        bbox: None,
+        features: vec![feature],
+        foreign_members: None,
+    }
+}
+
+/// distinct points of a geometry. repeated points, like the closing point of rings
+/// or nodes shared by ways, stop concave hulls from following the points
+fn points(geometry: &Geometry) -> MultiPoint {
+    let mut coords = geometry.coords_iter().collect::<Vec<_>>();
+    coords.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    coords.dedup();
+    coords.into_iter().map(Point::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{Area, Contains};
+
+    fn collection() -> FeatureCollection {
+        // the outline of a U, 0.01 degrees wide and tall, with a point every 0.001 degrees
+        let mut outline = vec![];
+        for i in 0..=10 {
+            let i = i as f64 * 0.001;
+            outline.push(vec![0.0, i]);
+            outline.push(vec![0.01, i]);
+            outline.push(vec![i, 0.0]);
+        }
+        for i in 0..=3 {
+            let i = i as f64 * 0.001;
+            outline.push(vec![i, 0.01]);
+            outline.push(vec![0.01 - i, 0.01]);
+        }
+        // the inside of the U
+        for i in 3..=9 {
+            let i = i as f64 * 0.001;
+            outline.push(vec![0.0035, i]);
+            outline.push(vec![0.0065, i]);
+        }
+        for i in 4..=6 {
+            outline.push(vec![i as f64 * 0.001, 0.003]);
+        }
+
+        serde_json::from_value(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "id": 1,
+                    "geometry": { "type": "MultiPoint", "coordinates": outline },
+                    "properties": { "name": "U" },
+                },
+                {
+                    "type": "Feature",
+                    "id": 2,
+                    "geometry": { "type": "LineString", "coordinates": [[1.0, 1.0], [1.0, 1.01]] },
+                    "properties": {},
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    fn area(feature: &Feature) -> f64 {
+        Geometry::<f64>::try_from(&feature.geometry.as_ref().unwrap().value)
+            .unwrap()
+            .unsigned_area()
+    }
+
+    #[test]
+    fn test_per_feature() {
+        let res = hull(collection(), HullKind::Convex, 2.0, true);
+        assert_eq!(res.features.len(), 2);
+        assert_eq!(
+            res.features[0].id,
+            Some(geojson::feature::Id::Number(1.into()))
+        );
+        assert_eq!(res.features[0].property("name"), Some(&"U".into()));
+        assert!((area(&res.features[0]) - 0.0001).abs() < 1e-9);
+
+        let res = hull(collection(), HullKind::Convex, 2.0, false);
+        assert_eq!(res.features.len(), 1);
+        assert_eq!(res.features[0].property("member_count"), Some(&2.into()));
+        assert!(area(&res.features[0]) > 0.0001);
+    }
+
+    #[test]
+    fn test_concave() {
+        let convex = hull(collection(), HullKind::Convex, 2.0, true);
+        let concave = hull(collection(), HullKind::Concave, 2.0, true);
+
+        // the inside of the U is left out of the concave hull
+        let notch = Point::new(0.005, 0.0095);
+        let geometry = |res: &FeatureCollection| {
+            Geometry::<f64>::try_from(&res.features[0].geometry.as_ref().unwrap().value).unwrap()
+        };
+        assert!(geometry(&convex).contains(&notch));
+        assert!(!geometry(&concave).contains(&notch));
+        assert!(area(&concave.features[0]) < area(&convex.features[0]));
+    }
+}
@@ -1,22 +1,173 @@
+use geo::{Centroid, HaversineDistance, Point};
+use geojson::{Feature, FeatureCollection};
 use serde::Deserialize;
 
-use crate::graph::{errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor};
+use crate::{
+    elevation::{CachedElevationMap, ElevationMap},
+    graph::{
+        errors::GraphError,
+        nodes::Node,
+        output::{NodeOutputs, DEFAULT_OUTPUT},
+        process::NodeProcessor,
+        Control,
+    },
+};
+
+/// Distance in meters between elevation samples along a sight line
+const SAMPLE_DISTANCE: f64 = 50.0;
+/// Earth radius in meters, corrected for atmospheric refraction (k = 0.13)
+const EFFECTIVE_EARTH_RADIUS: f64 = 6_371_000.0 / (1.0 - 0.13);
 
 #[derive(Deserialize, Debug)]
-pub struct InViewOf {}
+pub struct InViewOf {
+    /// Height of the observer above the ground, in meters
+    height: Control<f64>,
+    /// Maximum distance to look for `aux` features, in meters
+    max_distance: Control<f64>,
+}
 
 #[async_trait::async_trait]
 impl Node for InViewOf {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
-        let _input_collection = processor.get_input(node_id, "in").await?.into_features()?;
-        let _aux_collection = processor.get_input(node_id, "aux").await?.into_features()?;
+    ) -> Result<NodeOutputs, GraphError> {
+        let (input, aux) = tokio::try_join!(
+            processor.get_input(node_id, "in"),
+            processor.get_input(node_id, "aux")
+        )?;
+        let input_collection = input.into_features()?;
+        let aux_collection = aux.into_features()?;
+
+        let (height, max_distance) = (self.height.value, self.max_distance.value);
+        let id = node_id.to_string();
+
+        let (visible, rejected) = processor
+            .with_elevation(move |map| {
+                filter(
+                    input_collection,
+                    aux_collection,
+                    height,
+                    max_distance,
+                    &id,
+                    map,
+                )
+            })
+            .await??;
+        Ok(NodeOutputs::new()
+            .with(DEFAULT_OUTPUT, visible)
+            .with("rejected", rejected))
+    }
+}
 
-        // TODO filter input_collection by whether it can see the aux_collection
+fn filter(
+    collection: FeatureCollection,
+    aux: FeatureCollection,
+    height: f64,
+    max_distance: f64,
+    node_id: &str,
+    map: &ElevationMap,
+) -> Result<(FeatureCollection, FeatureCollection), GraphError> {
+    let _span = tracing::trace_span!("in_view_of::filter");
+    let _span = _span.enter();
 
-        todo!()
+    // TODO add client-side validation too
+
+    if height < 0.0 {
+        Err(GraphError::InViewOf {
+            message: "Height has to be a positive value".to_string(),
+            node_id: node_id.to_string(),
+        })?;
+    }
+
+    if max_distance <= 0.0 {
+        Err(GraphError::InViewOf {
+            message: "Max distance has to be greater than 0".to_string(),
+            node_id: node_id.to_string(),
+        })?;
     }
+
+    let mut map = map.cached();
+
+    let targets = aux
+        .features
+        .iter()
+        .filter_map(representative_point)
+        .map(|p| (p, map.lookup_or_0(p.x(), p.y())))
+        .collect::<Vec<_>>();
+
+    let (visible, rejected): (Vec<_>, Vec<_>) =
+        collection.features.into_iter().partition(|feature| {
+            let Some(observer) = representative_point(feature) else {
+                return false;
+            };
+            let observer_elevation = map.lookup_or_0(observer.x(), observer.y()) + height;
+
+            targets.iter().any(|(target, target_elevation)| {
+                let distance = observer.haversine_distance(target);
+                distance <= max_distance
+                    && is_visible(
+                        &mut map,
+                        observer,
+                        observer_elevation,
+                        *target,
+                        *target_elevation,
+                        distance,
+                    )
+            })
+        });
+
+    tracing::trace!("there were {} cached datasets", map.cache.len());
+
+    Ok((
+        FeatureCollection {
+            bbox: None,
+            features: visible,
+            foreign_members: None,
+        },
+        FeatureCollection {
+            bbox: None,
+            features: rejected,
+            foreign_members: None,
+        },
+    ))
+}
+
+/// returns the point used as the position of the feature
+fn representative_point(feature: &Feature) -> Option<Point> {
+    let geometry = feature.geometry.as_ref()?;
+    let geometry: geo::Geometry = (&geometry.value).try_into().ok()?;
+    geometry.centroid()
+}
+
+/// checks whether the terrain between `from` and `to` blocks the straight line between them
+///
+/// `distance` is the distance in meters between both points
+fn is_visible(
+    map: &mut CachedElevationMap<'_>,
+    from: Point,
+    from_elevation: f64,
+    to: Point,
+    to_elevation: f64,
+    distance: f64,
+) -> bool {
+    let samples = (distance / SAMPLE_DISTANCE).ceil() as usize;
+
+    // skip the first and last samples, since those are the points themselves
+    (1..samples).all(|i| {
+        let t = i as f64 / samples as f64;
+        let lng = from.x() + (to.x() - from.x()) * t;
+        let lat = from.y() + (to.y() - from.y()) * t;
+
+        let sight_line = from_elevation + (to_elevation - from_elevation) * t;
+
+        // the earth curves away from the sight line
+        let travelled = distance * t;
+        let curvature = travelled * (distance - travelled) / (2.0 * EFFECTIVE_EARTH_RADIUS);
+
+        let terrain = map.lookup_or_0(lng, lat) + curvature;
+
+        terrain <= sight_line
+    })
 }
@@ -0,0 +1,36 @@
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::NodeOutputs,
+    process::NodeProcessor,
+    utils::{retain_by_identity, FeatureIdentity},
+    Control,
+};
+
+/// Features of `a` that are also in `b`
+#[derive(Deserialize, Debug)]
+pub struct Intersection {
+    compare: Option<Control<FeatureIdentity>>,
+}
+
+#[async_trait::async_trait]
+impl Node for Intersection {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let (a, b) = tokio::try_join!(
+            processor.get_input(node_id, "a"),
+            processor.get_input(node_id, "b")
+        )?;
+        let mut a_collection = a.into_features()?;
+        let b_collection = b.into_features()?;
+
+        let identity = self.compare.as_ref().map(|c| c.value).unwrap_or_default();
+        retain_by_identity(&mut a_collection, &b_collection, identity, true);
+        Ok(a_collection.into())
+    }
+}
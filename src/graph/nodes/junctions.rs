@@ -0,0 +1,256 @@
+use std::collections::BTreeMap;
+
+use geo::{GeodesicBearing, Point};
+use geojson::{feature::Id, Feature, FeatureCollection, JsonValue, Value};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::NodeOutputs,
+    process::NodeProcessor,
+    utils::{new_id, round, JUNCTION_NUMBER},
+    Control,
+};
+
+/// Finds the osm nodes shared by several ways, from the `__children_ids` of the ways
+#[derive(Deserialize, Debug)]
+pub struct Junctions {
+    /// amount of way segments leaving the junction, a way passing through it counts twice
+    min_degree: Control<usize>,
+    max_degree: Control<usize>,
+    /// in degrees. bounds for the smallest angle between two neighbouring segments
+    min_angle: Control<f64>,
+    max_angle: Control<f64>,
+}
+
+/// A way segment leaving a junction
+#[derive(Debug)]
+struct Arm {
+    way_id: JsonValue,
+    /// in degrees, from 0 to 360
+    bearing: f64,
+}
+
+#[async_trait::async_trait]
+impl Node for Junctions {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        // TODO add client-side validation too
+
+        if self.min_degree.value > self.max_degree.value {
+            Err(GraphError::Junction {
+                message: "The min degree has a greater value than the max degree".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        if self.min_angle.value > self.max_angle.value {
+            Err(GraphError::Junction {
+                message: "The min angle has a greater value than the max angle".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        let (min_degree, max_degree, min_angle, max_angle) = (
+            self.min_degree.value,
+            self.max_degree.value,
+            self.min_angle.value,
+            self.max_angle.value,
+        );
+
+        let features = tokio::task::spawn_blocking(move || {
+            junctions(&collection)
+                .into_iter()
+                .filter(|j| (min_degree..=max_degree).contains(&j.degree))
+                .filter(|j| (min_angle..=max_angle).contains(&j.min_angle()))
+                .map(Junction::into_feature)
+                .collect()
+        })
+        .await?;
+
+        Ok(FeatureCollection {
+            bbox: None,
+            features,
+            foreign_members: None,
+        }
+        .into())
+    }
+}
+
+#[derive(Debug)]
+struct Junction {
+    osm_id: u64,
+    point: Point,
+    degree: usize,
+    way_ids: Vec<JsonValue>,
+    /// in degrees, between neighbouring segments going clockwise
+    angles: Vec<f64>,
+}
+
+impl Junction {
+    fn min_angle(&self) -> f64 {
+        self.angles.iter().copied().fold(f64::INFINITY, f64::min)
+    }
+
+    fn into_feature(self) -> Feature {
+        let mut feature = Feature {
+            id: new_id(Id::Number(self.osm_id.into()), JUNCTION_NUMBER),
+            geometry: Some(Value::from(&self.point).into()),
+            ..Default::default()
+        };
+        feature.set_property("osm_id", self.osm_id);
+        feature.set_property("degree", self.degree);
+        feature.set_property("min_angle", round(self.min_angle()));
+        feature.set_property("way_ids", self.way_ids);
+        feature.set_property(
+            "angles",
+            self.angles.into_iter().map(round).collect::<Vec<_>>(),
+        );
+        feature
+    }
+}
+
+/// every osm node shared by at least two ways
+fn junctions(collection: &FeatureCollection) -> Vec<Junction> {
+    let _span = tracing::trace_span!("junctions::junctions");
+    let _span = _span.enter();
+
+    // osm node id -> its coordinates, and the segments leaving it
+    let mut nodes: BTreeMap<u64, (Point, Vec<Arm>)> = BTreeMap::new();
+
+    for way in &collection.features {
+        let Some((way_id, children, coords)) = way_nodes(way) else {
+            continue;
+        };
+
+        for (i, (child, coord)) in children.iter().zip(&coords).enumerate() {
+            let neighbours = [i.checked_sub(1), Some(i + 1)];
+            let arms = neighbours
+                .into_iter()
+                .flatten()
+                .filter_map(|n| coords.get(n))
+                .filter(|n| *n != coord)
+                .map(|n| Arm {
+                    way_id: way_id.clone(),
+                    bearing: coord.geodesic_bearing(*n).rem_euclid(360.0),
+                });
+
+            nodes
+                .entry(*child)
+                .or_insert_with(|| (*coord, vec![]))
+                .1
+                .extend(arms);
+        }
+    }
+
+    nodes
+        .into_iter()
+        .filter_map(|(id, (point, mut arms))| {
+            let mut way_ids = arms.iter().map(|a| a.way_id.clone()).collect::<Vec<_>>();
+            way_ids.dedup();
+            if way_ids.len() < 2 {
+                return None;
+            }
+
+            arms.sort_by(|a, b| a.bearing.total_cmp(&b.bearing));
+            Some(Junction {
+                osm_id: id,
+                point,
+                degree: arms.len(),
+                way_ids,
+                angles: angles(&arms.iter().map(|a| a.bearing).collect::<Vec<_>>()),
+            })
+        })
+        .collect()
+}
+
+/// osm id, node ids and coordinates of a way, if it has a coordinate for every node
+fn way_nodes(way: &Feature) -> Option<(JsonValue, Vec<u64>, Vec<Point>)> {
+    let properties = way.properties.as_ref()?;
+    let children = properties
+        .get("__children_ids")?
+        .as_array()?
+        .iter()
+        .map(|id| id.as_u64())
+        .collect::<Option<Vec<_>>>()?;
+
+    let coords = match &way.geometry.as_ref()?.value {
+        Value::LineString(coords) => coords,
+        Value::Polygon(rings) => rings.first()?,
+        _ => return None,
+    };
+    // nodes without coordinates are skipped when converting to geojson, so we couldn't match them
+    if coords.len() != children.len() {
+        return None;
+    }
+
+    let way_id = properties.get("osm_id").cloned().unwrap_or(JsonValue::Null);
+    let coords = coords.iter().map(|c| Point::new(c[0], c[1])).collect();
+    Some((way_id, children, coords))
+}
+
+/// angles between neighbouring sorted bearings, including the one between the last and the first
+fn angles(bearings: &[f64]) -> Vec<f64> {
+    match bearings {
+        [] => vec![],
+        [_] => vec![360.0],
+        [first, .., last] => bearings
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .chain([360.0 - last + first])
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn way(osm_id: u64, children: &[u64], coords: &[[f64; 2]]) -> Feature {
+        let mut feature = Feature {
+            geometry: Some(Value::LineString(coords.iter().map(|c| c.to_vec()).collect()).into()),
+            ..Default::default()
+        };
+        feature.set_property("osm_id", osm_id);
+        feature.set_property("__children_ids", children.to_vec());
+        feature
+    }
+
+    #[test]
+    fn test_junctions() {
+        // a crossing at node 1, and a dead end at node 5 that only one way reaches
+        let collection = FeatureCollection {
+            bbox: None,
+            features: vec![
+                way(10, &[2, 1, 3], &[[-0.001, 0.0], [0.0, 0.0], [0.001, 0.0]]),
+                way(11, &[4, 1], &[[0.0, -0.001], [0.0, 0.0]]),
+                way(12, &[1, 5], &[[0.0, 0.0], [0.0, 0.001]]),
+            ],
+            foreign_members: None,
+        };
+
+        let junctions = junctions(&collection);
+        assert_eq!(junctions.len(), 1);
+
+        let junction = &junctions[0];
+        assert_eq!(junction.osm_id, 1);
+        assert_eq!(junction.degree, 4);
+        assert_eq!(
+            junction.way_ids,
+            vec![JsonValue::from(10), 11.into(), 12.into()]
+        );
+        assert!((junction.min_angle() - 90.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_angles() {
+        assert_eq!(angles(&[10.0, 100.0, 300.0]), vec![90.0, 200.0, 70.0]);
+        assert_eq!(angles(&[45.0]), vec![360.0]);
+    }
+}
@@ -1,17 +1,40 @@
 use serde::Deserialize;
 
-use crate::graph::{errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor};
+use crate::graph::{
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor, Control,
+};
 
 #[derive(Deserialize, Debug)]
-pub struct Map {}
+pub struct Map {
+    /// Name of the layer the results are shown as
+    name: Option<Control<String>>,
+    /// Color to display the layer with, as a css color
+    color: Option<Control<String>>,
+}
+
+impl Map {
+    pub fn layer_name(&self) -> Option<&str> {
+        self.name
+            .as_ref()
+            .map(|n| n.value.as_str())
+            .filter(|n| !n.is_empty())
+    }
+
+    pub fn color(&self) -> Option<&str> {
+        self.color
+            .as_ref()
+            .map(|c| c.value.as_str())
+            .filter(|c| !c.is_empty())
+    }
+}
 
 #[async_trait::async_trait]
 impl Node for Map {
     async fn process(
         &self,
-        _processor: &mut NodeProcessor<'_>,
+        _processor: &NodeProcessor<'_>,
         _node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         unreachable!()
     }
 }
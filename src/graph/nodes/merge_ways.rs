@@ -0,0 +1,358 @@
+use std::collections::{BTreeMap, HashMap};
+
+use geojson::{Feature, FeatureCollection, JsonValue, Value};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::NodeOutputs,
+    process::NodeProcessor,
+    utils::{new_id, MERGE_NUMBER},
+    Control,
+};
+
+/// Merges LineStrings that share an endpoint and have the same values for `keys` into longer
+/// LineStrings. Where the lines branch, every branch is its own feature.
+/// `way_ids` has the `osm_id` of the lines merged into a feature
+#[derive(Deserialize, Debug)]
+pub struct MergeWays {
+    /// comma separated tags that have to match, like `name,highway`.
+    /// lines without some of them aren't merged, and with no keys all connected lines are merged
+    keys: Control<String>,
+}
+
+#[async_trait::async_trait]
+impl Node for MergeWays {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        let keys = self
+            .keys
+            .value
+            .split(',')
+            .map(|k| k.trim().to_string())
+            .filter(|k| !k.is_empty())
+            .collect::<Vec<_>>();
+
+        let res = tokio::task::spawn_blocking(move || merge(collection, &keys)).await?;
+        Ok(res.into())
+    }
+}
+
+/// exact position of a coordinate, to find the lines sharing an endpoint
+type Endpoint = (u64, u64);
+
+fn endpoint(coord: &[f64]) -> Endpoint {
+    (coord[0].to_bits(), coord[1].to_bits())
+}
+
+fn merge(collection: FeatureCollection, keys: &[String]) -> FeatureCollection {
+    let _span = tracing::trace_span!("merge_ways::merge");
+    let _span = _span.enter();
+
+    // other geometries, and lines without all the keys, are passed through as they are
+    let (lines, mut features): (Vec<_>, Vec<_>) = collection.features.into_iter().partition(|f| {
+        let is_line = matches!(
+            f.geometry.as_ref().map(|g| &g.value),
+            Some(Value::LineString(coords)) if coords.len() >= 2
+        );
+        is_line && keys.iter().all(|k| property(f, k).is_some())
+    });
+
+    // tag values -> lines with them
+    let mut groups: BTreeMap<String, Vec<Feature>> = BTreeMap::new();
+    for line in lines {
+        let tags = keys
+            .iter()
+            .map(|k| property(&line, k).unwrap_or(JsonValue::Null))
+            .collect::<Vec<_>>();
+        groups
+            .entry(JsonValue::from(tags).to_string())
+            .or_default()
+            .push(line);
+    }
+
+    for lines in groups.into_values() {
+        features.extend(merge_group(lines));
+    }
+
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+/// merges lines with the same tags into continuous lines
+fn merge_group(lines: Vec<Feature>) -> Vec<Feature> {
+    let coords = lines.iter().map(line_coords).collect::<Vec<_>>();
+
+    // endpoint -> lines starting or ending there
+    let mut ends: HashMap<Endpoint, Vec<usize>> = HashMap::new();
+    for (i, c) in coords.iter().enumerate() {
+        ends.entry(endpoint(&c[0])).or_default().push(i);
+        ends.entry(endpoint(&c[c.len() - 1])).or_default().push(i);
+    }
+
+    let mut used = vec![false; lines.len()];
+    let mut merged = vec![];
+
+    for start in start_order(&coords, &ends) {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let mut members = vec![start];
+        let mut chain = coords[start].clone();
+        for _ in 0..2 {
+            loop {
+                // chains stop at dead ends and at branches, so every branch is its own feature
+                let at_end = &ends[&endpoint(&chain[chain.len() - 1])];
+                if at_end.len() != 2 {
+                    break;
+                }
+                let Some(next) = at_end.iter().copied().find(|i| !used[*i]) else {
+                    break;
+                };
+                used[next] = true;
+                members.push(next);
+
+                let mut next_coords = coords[next].clone();
+                if endpoint(&next_coords[0]) != endpoint(&chain[chain.len() - 1]) {
+                    next_coords.reverse();
+                }
+                chain.extend(next_coords.into_iter().skip(1));
+            }
+            // then extend the start of the chain
+            chain.reverse();
+        }
+
+        merged.push(merged_feature(&lines, &members, chain));
+    }
+
+    merged
+}
+
+/// lines starting at a dead end or a branch go first, so loops are opened where they join others
+fn start_order(coords: &[Vec<Vec<f64>>], ends: &HashMap<Endpoint, Vec<usize>>) -> Vec<usize> {
+    let mut order = (0..coords.len()).collect::<Vec<_>>();
+    order.sort_by_key(|i| {
+        let c = &coords[*i];
+        let continues = |e: &Vec<f64>| ends[&endpoint(e)].len() == 2;
+        continues(&c[0]) && continues(&c[c.len() - 1])
+    });
+    order
+}
+
+fn merged_feature(lines: &[Feature], members: &[usize], chain: Vec<Vec<f64>>) -> Feature {
+    let first = &lines[members[0]];
+    if members.len() == 1 {
+        let mut feature = first.clone();
+        if let Some(osm_id) = property(first, "osm_id") {
+            feature.set_property("way_ids", vec![osm_id]);
+        }
+        return feature;
+    }
+
+    // only the properties all the lines agree on are kept
+    let mut properties = first.properties.clone().unwrap_or_default();
+    for i in &members[1..] {
+        let other = lines[*i].properties.as_ref();
+        properties = properties
+            .into_iter()
+            .filter(|(k, v)| other.and_then(|o| o.get(k)) == Some(v))
+            .collect();
+    }
+    // node ids wouldn't match the merged geometry
+    properties.remove("__children_ids");
+
+    let way_ids = members
+        .iter()
+        .filter_map(|i| property(&lines[*i], "osm_id"))
+        .collect::<Vec<_>>();
+    properties.insert("way_ids".to_string(), way_ids.into());
+
+    Feature {
+        id: first.id.clone().and_then(|id| new_id(id, MERGE_NUMBER)),
+        geometry: Some(Value::LineString(chain).into()),
+        properties: Some(properties),
+        ..Default::default()
+    }
+}
+
+fn line_coords(line: &Feature) -> Vec<Vec<f64>> {
+    match line.geometry.as_ref().map(|g| &g.value) {
+        Some(Value::LineString(coords)) => coords.clone(),
+        _ => unreachable!("only linestrings are merged"),
+    }
+}
+
+/// value of a property, `None` if it's missing or null
+fn property(feature: &Feature, key: &str) -> Option<JsonValue> {
+    feature.property(key).filter(|v| !v.is_null()).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::nodes::road_length_filter;
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    fn collection() -> FeatureCollection {
+        serde_json::from_value(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.0, 0.0], [0.001, 0.0]] },
+                    "properties": { "osm_id": 1, "name": "Carrer Gran", "__children_ids": [1, 2] },
+                },
+                // reversed, and not in order
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.003, 0.0], [0.002, 0.0]] },
+                    "properties": { "osm_id": 3, "name": "Carrer Gran", "__children_ids": [4, 3] },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.001, 0.0], [0.002, 0.0]] },
+                    "properties": { "osm_id": 2, "name": "Carrer Gran", "__children_ids": [2, 3] },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.003, 0.0], [0.004, 0.0]] },
+                    "properties": { "osm_id": 8, "name": "Carrer Gran", "__children_ids": [4, 9] },
+                },
+                // the street splits in two branches, which are their own features
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.004, 0.0], [0.004, -0.001]] },
+                    "properties": { "osm_id": 4, "name": "Carrer Gran", "__children_ids": [9, 5] },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.004, 0.0], [0.005, 0.0]] },
+                    "properties": { "osm_id": 9, "name": "Carrer Gran", "__children_ids": [9, 10] },
+                },
+                // connected, but with another name
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.003, 0.0], [0.003, 0.001]] },
+                    "properties": { "osm_id": 5, "name": "Carrer Nou", "__children_ids": [4, 6] },
+                },
+                // without a name, so never merged
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.003, 0.001], [0.004, 0.001]] },
+                    "properties": { "osm_id": 6, "__children_ids": [6, 7] },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[0.004, 0.001], [0.005, 0.001]] },
+                    "properties": { "osm_id": 7, "__children_ids": [7, 8] },
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    /// ways merged into a feature, or the way itself if it was passed through
+    fn way_ids(feature: &Feature) -> Vec<u64> {
+        let mut ids = match feature.property("way_ids") {
+            Some(ids) => ids.as_array().unwrap().clone(),
+            None => vec![feature.property("osm_id").unwrap().clone()],
+        }
+        .iter()
+        .map(|id| id.as_u64().unwrap())
+        .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_merge() {
+        let res = merge(collection(), &["name".to_string()]);
+        let mut merged = res.features.iter().map(way_ids).collect::<Vec<_>>();
+        merged.sort();
+        assert_eq!(
+            merged,
+            vec![
+                vec![1, 2, 3, 8],
+                vec![4],
+                vec![5],
+                vec![6],
+                vec![7],
+                vec![9]
+            ]
+        );
+
+        let gran = res.features.iter().find(|f| way_ids(f).len() == 4).unwrap();
+        assert_eq!(
+            gran.geometry.as_ref().unwrap().value,
+            Value::LineString(vec![
+                vec![0.0, 0.0],
+                vec![0.001, 0.0],
+                vec![0.002, 0.0],
+                vec![0.003, 0.0],
+                vec![0.004, 0.0]
+            ])
+        );
+        assert_eq!(gran.property("osm_id"), None);
+        assert_eq!(gran.property("__children_ids"), None);
+        assert_eq!(gran.property("name"), Some(&"Carrer Gran".into()));
+
+        // without keys, the unnamed lines are merged too
+        let res = merge(collection(), &[]);
+        assert!(res
+            .features
+            .iter()
+            .any(|f| way_ids(f).contains(&6) && way_ids(f).contains(&7)));
+    }
+
+    #[test]
+    fn test_merge_input_order() {
+        // merged ways and their points, ignoring the order and direction of the lines
+        let merged = |collection| {
+            let res = merge(collection, &["name".to_string()]);
+            let mut merged = res
+                .features
+                .iter()
+                .map(|f| {
+                    let mut coords = line_coords(f);
+                    coords.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                    (way_ids(f), coords)
+                })
+                .collect::<Vec<_>>();
+            merged.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            merged
+        };
+
+        let expected = merged(collection());
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..20 {
+            let mut shuffled = collection();
+            shuffled.features.shuffle(&mut rng);
+            assert_eq!(merged(shuffled), expected);
+        }
+    }
+
+    #[test]
+    fn test_road_length_after_merge() {
+        // 0.001 degrees are around 110m, so only the merged street is longer than 300m
+        let length_filter = |collection| {
+            road_length_filter::filter(collection, 300.0, 1000.0, 10.0, "node")
+                .unwrap()
+                .features
+                .len()
+        };
+
+        assert_eq!(length_filter(collection()), 0);
+        assert_eq!(length_filter(merge(collection(), &["name".to_string()])), 1);
+    }
+}
@@ -1,8 +1,18 @@
-use super::{errors::GraphError, output::NodeOutput, process::NodeProcessor};
+use super::{errors::GraphError, output::NodeOutputs, process::NodeProcessor};
 
+pub mod area_filter;
+pub mod buffer;
+pub mod centroid;
+pub mod cluster;
+pub mod difference;
 pub mod elevation_filter;
+pub mod elevation_profile;
+pub mod hull;
 pub mod in_view_of;
+pub mod intersection;
+pub mod junctions;
 pub mod map;
+pub mod merge_ways;
 pub mod oql;
 pub mod oql_difference;
 pub mod oql_statement;
@@ -10,15 +20,19 @@ pub mod oql_union;
 pub mod overpass;
 pub mod road_angle_filter;
 pub mod road_length_filter;
+pub mod simplify;
+pub mod slope_filter;
+pub mod spatial_filter;
+pub mod tag_filter;
 pub mod union;
 
 #[async_trait::async_trait]
 pub trait Node {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError>;
+    ) -> Result<NodeOutputs, GraphError>;
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -54,16 +68,36 @@ pub enum GraphNodeInternal {
     #[serde(rename = "Elevation Filter")]
     ElevationFilter(elevation_filter::ElevationFilter),
     Union(union::Union),
+    Difference(difference::Difference),
+    Intersection(intersection::Intersection),
+    #[serde(rename = "In View Of")]
     InViewOf(in_view_of::InViewOf),
+    #[serde(rename = "Slope Filter")]
+    SlopeFilter(slope_filter::SlopeFilter),
+    #[serde(rename = "Elevation Profile")]
+    ElevationProfile(elevation_profile::ElevationProfile),
+    #[serde(rename = "Tag Filter")]
+    TagFilter(tag_filter::TagFilter),
+    #[serde(rename = "Within Distance")]
+    WithinDistance(spatial_filter::WithinDistance),
+    Intersects(spatial_filter::Intersects),
+    Inside(spatial_filter::Inside),
+    Cluster(cluster::Cluster),
+    Buffer(buffer::Buffer),
+    Centroid(centroid::Centroid),
+    Hull(hull::Hull),
+    Simplify(simplify::Simplify),
+    #[serde(rename = "Area Filter")]
+    AreaFilter(area_filter::AreaFilter),
+    Junctions(junctions::Junctions),
+    #[serde(rename = "Merge Ways")]
+    MergeWays(merge_ways::MergeWays),
 }
 
 // TODO use a macro to generate all of this
 // the ambassador crate did not work with async_trait fsr
 impl GraphNode {
-    pub async fn process(
-        &self,
-        processor: &mut NodeProcessor<'_>,
-    ) -> Result<NodeOutput, GraphError> {
+    pub async fn process(&self, processor: &NodeProcessor<'_>) -> Result<NodeOutputs, GraphError> {
         match &self.node {
             GraphNodeInternal::Map(m) => m.process(processor, &self.id).await,
             GraphNodeInternal::Oql(m) => m.process(processor, &self.id).await,
@@ -75,7 +109,23 @@ impl GraphNode {
             GraphNodeInternal::RoadLengthFilter(m) => m.process(processor, &self.id).await,
             GraphNodeInternal::ElevationFilter(m) => m.process(processor, &self.id).await,
             GraphNodeInternal::Union(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Difference(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Intersection(m) => m.process(processor, &self.id).await,
             GraphNodeInternal::InViewOf(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::SlopeFilter(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::ElevationProfile(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::TagFilter(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::WithinDistance(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Intersects(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Inside(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Cluster(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Buffer(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Centroid(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Hull(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Simplify(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::AreaFilter(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::Junctions(m) => m.process(processor, &self.id).await,
+            GraphNodeInternal::MergeWays(m) => m.process(processor, &self.id).await,
         }
     }
 }
@@ -1,7 +1,7 @@
 use serde::Deserialize;
 
 use crate::graph::{
-    errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor, Control,
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor, Control,
 };
 
 #[derive(Deserialize, Debug)]
@@ -13,9 +13,9 @@ pub struct Oql {
 impl Node for Oql {
     async fn process(
         &self,
-        _processor: &mut NodeProcessor<'_>,
+        _processor: &NodeProcessor<'_>,
         _node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         Ok(self.query.value.clone().into())
     }
 }
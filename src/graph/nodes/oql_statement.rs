@@ -1,7 +1,7 @@
 use serde::Deserialize;
 
 use crate::graph::{
-    errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor, Control,
+    errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor, Control,
 };
 
 #[derive(Deserialize, Debug)]
@@ -18,9 +18,9 @@ pub struct OqlStatement {
 impl Node for OqlStatement {
     async fn process(
         &self,
-        _processor: &mut NodeProcessor<'_>,
+        _processor: &NodeProcessor<'_>,
         _node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         let f = match (self.nodes.value, self.ways.value, self.relations.value) {
             (true, true, true) => "nwr",
             (true, true, false) => "nw",
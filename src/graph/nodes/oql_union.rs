@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-use crate::graph::{errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor};
+use crate::graph::{errors::GraphError, nodes::Node, output::NodeOutputs, process::NodeProcessor};
 
 #[derive(Deserialize, Debug)]
 pub struct OqlUnion {}
@@ -9,11 +9,14 @@ pub struct OqlUnion {}
 impl Node for OqlUnion {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
-        let a = processor.get_input(node_id, "a").await?.into_query()?;
-        let b = processor.get_input(node_id, "b").await?.into_query()?;
+    ) -> Result<NodeOutputs, GraphError> {
+        let (a, b) = tokio::try_join!(
+            processor.get_input(node_id, "a"),
+            processor.get_input(node_id, "b")
+        )?;
+        let (a, b) = (a.into_query()?, b.into_query()?);
 
         let query = format!("({a} {b});");
 
@@ -2,9 +2,16 @@ use geojson::FeatureCollection;
 use serde::Deserialize;
 
 use crate::{
-    graph::{errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor, Control},
+    graph::{
+        errors::GraphError,
+        nodes::Node,
+        output::NodeOutputs,
+        process::{NodeEvent, NodeProcessor},
+        Control,
+    },
     nominatim::OsmNominatim,
-    osm_to_geojson::{osm_to_geojson, Osm},
+    osm_to_geojson::osm_to_geojson,
+    overpass::{OverpassClient, OverpassError},
     preprocess::preprocess_query,
     search::{Bbox, GeocodeaArea},
 };
@@ -18,60 +25,66 @@ pub struct Overpass {
 impl Node for Overpass {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         let query = processor.get_input(node_id, "query").await?.into_query()?;
 
         // cache
         let bbox = processor.bbox;
-        let (feature_collection, found_areas, query) = processor
+        let client = processor.overpass;
+        let key = (query.clone(), processor.bbox);
+        if processor.caches.overpass.contains_key(&key) {
+            processor.emit(NodeEvent::CacheHit {
+                node_id: node_id.to_string(),
+            });
+        }
+
+        let (feature_collection, found_areas, query, endpoint) = processor
             .caches
-            .overpass
-            .try_get_with((query.clone(), processor.bbox), async move {
-                run(&query, bbox, self.timeout.value, node_id).await
+            .get_overpass(key, async move {
+                run(client, &query, bbox, self.timeout.value, node_id).await
             })
             .await?;
 
-        processor.geocode_areas.extend(found_areas);
+        processor.geocode_areas.lock().unwrap().extend(found_areas);
         processor
             .processed_queries
+            .lock()
+            .unwrap()
             .insert(node_id.to_string(), query);
+        processor
+            .endpoints
+            .lock()
+            .unwrap()
+            .insert(node_id.to_string(), endpoint);
 
         Ok(feature_collection.into())
     }
 }
 
 async fn run(
+    client: &OverpassClient,
     query: &str,
     bbox: Bbox,
     timeout: u32,
     node_id: &str,
-) -> Result<(FeatureCollection, Vec<GeocodeaArea>, String), GraphError> {
+) -> Result<(FeatureCollection, Vec<GeocodeaArea>, String, String), GraphError> {
     let (query, found_areas) = preprocess_query(query, &bbox, timeout, OsmNominatim).await?;
 
-    let client = reqwest::Client::new();
-    let res = client
-        .post("https://overpass-api.de/api/interpreter")
-        .body(query.clone())
-        .send()
-        .await?;
-
-    if res.status() != 200 {
-        let res = res.text().await?;
-        return Err(GraphError::OqlSyntax {
-            node_id: node_id.to_string(),
-            error: res,
-            query,
-        });
-    }
-
-    let osm: Osm = res
-        .json()
-        .await
-        .map_err(|_| GraphError::OverpassJsonError)?;
+    let (osm, endpoint) = match client.query(&query, timeout).await {
+        Ok(res) => res,
+        Err(OverpassError::Syntax(error)) => {
+            return Err(GraphError::OqlSyntax {
+                node_id: node_id.to_string(),
+                error,
+                query,
+            })
+        }
+        Err(err) => return Err(err.into()),
+    };
 
     let feature_collection = osm_to_geojson(osm);
 
-    Ok((feature_collection, found_areas, query))
+    Ok((feature_collection, found_areas, query, endpoint))
 }
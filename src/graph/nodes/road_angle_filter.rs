@@ -1,7 +1,7 @@
 use crate::graph::{
     errors::GraphError,
     nodes::Node,
-    output::NodeOutput,
+    output::NodeOutputs,
     process::NodeProcessor,
     utils::{new_id, RAF_NUMBER},
     Control,
@@ -21,9 +21,9 @@ pub struct RoadAngleFilter {
 impl Node for RoadAngleFilter {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         let collection = processor.get_input(node_id, "in").await?.into_features()?;
 
         let res = filter(collection, self.min.value, self.max.value, node_id)?;
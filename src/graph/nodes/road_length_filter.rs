@@ -1,7 +1,7 @@
 use crate::graph::{
     errors::GraphError,
     nodes::Node,
-    output::NodeOutput,
+    output::NodeOutputs,
     process::NodeProcessor,
     utils::{bearing_distance, new_id, RLF_NUMBER},
     Control,
@@ -21,9 +21,9 @@ pub struct RoadLengthFilter {
 impl Node for RoadLengthFilter {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
+    ) -> Result<NodeOutputs, GraphError> {
         let collection = processor.get_input(node_id, "in").await?.into_features()?;
 
         let res = filter(
@@ -37,7 +37,7 @@ impl Node for RoadLengthFilter {
     }
 }
 
-fn filter(
+pub(super) fn filter(
     collection: FeatureCollection,
     min: f64,
     max: f64,
@@ -0,0 +1,76 @@
+use geo::{Geometry, Simplify as _};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::NodeOutputs,
+    process::NodeProcessor,
+    utils::{map_geometries, METERS_PER_DEGREE},
+    Control,
+};
+
+/// Simplifies lines and polygons with the Douglas-Peucker algorithm
+#[derive(Deserialize, Debug)]
+pub struct Simplify {
+    /// in meters
+    tolerance: Control<f64>,
+}
+
+#[async_trait::async_trait]
+impl Node for Simplify {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        // TODO add client-side validation too
+
+        if self.tolerance.value < 0.0 {
+            Err(GraphError::Transform {
+                message: "Tolerance has to be a positive value".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        let tolerance = self.tolerance.value;
+        let res = map_geometries(collection, |g| Some(simplify(g, tolerance)));
+        Ok(res.into())
+    }
+}
+
+/// simplifies lines and polygons, with `tolerance` in meters
+fn simplify(geometry: Geometry, tolerance: f64) -> Geometry {
+    // the algorithm works in degrees, using the size of a degree of latitude is close enough
+    let epsilon = tolerance / METERS_PER_DEGREE;
+
+    match geometry {
+        Geometry::LineString(g) => g.simplify(&epsilon).into(),
+        Geometry::MultiLineString(g) => g.simplify(&epsilon).into(),
+        Geometry::Polygon(g) => g.simplify(&epsilon).into(),
+        Geometry::MultiPolygon(g) => g.simplify(&epsilon).into(),
+        Geometry::GeometryCollection(g) => {
+            Geometry::GeometryCollection(g.into_iter().map(|g| simplify(g, tolerance)).collect())
+        }
+        // points can't be simplified
+        g => g,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_string, CoordsIter};
+
+    #[test]
+    fn test_simplify() {
+        // the middle point is around 3m off the line between the others
+        let line: Geometry =
+            line_string![(x: 2.0, y: 41.0), (x: 2.001, y: 41.00003), (x: 2.002, y: 41.0)].into();
+
+        assert_eq!(simplify(line.clone(), 1.0).coords_count(), 3);
+        assert_eq!(simplify(line, 5.0).coords_count(), 2);
+    }
+}
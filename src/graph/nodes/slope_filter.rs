@@ -0,0 +1,154 @@
+use crate::{
+    elevation::ElevationMap,
+    graph::{
+        errors::GraphError,
+        nodes::Node,
+        output::NodeOutputs,
+        process::NodeProcessor,
+        utils::{new_id, SLOPE_NUMBER},
+        Control,
+    },
+};
+use geo::{GeodesicDistance, Point};
+use geojson::{Feature, FeatureCollection, Value};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct SlopeFilter {
+    /// Minimum grade, in percent
+    min: Control<f64>,
+    /// Maximum grade, in percent
+    max: Control<f64>,
+    /// Whether to join consecutive matching segments into a single line
+    merge: Control<bool>,
+}
+
+#[async_trait::async_trait]
+impl Node for SlopeFilter {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        let (min, max, merge) = (self.min.value, self.max.value, self.merge.value);
+        let id = node_id.to_string();
+
+        let res = processor
+            .with_elevation(move |map| filter(collection, min, max, merge, &id, map))
+            .await??;
+        Ok(res.into())
+    }
+}
+
+fn filter(
+    collection: FeatureCollection,
+    min: f64,
+    max: f64,
+    merge: bool,
+    node_id: &str,
+    map: &ElevationMap,
+) -> Result<FeatureCollection, GraphError> {
+    let _span = tracing::trace_span!("slope_filter::filter");
+    let _span = _span.enter();
+
+    // TODO add client-side validation too
+
+    if min > max {
+        Err(GraphError::Slope {
+            message: "The min flag has a greater value than the max flag".to_string(),
+            node_id: node_id.to_string(),
+        })?;
+    }
+
+    if min < 0.0 || max < 0.0 {
+        Err(GraphError::Slope {
+            message: "Min and Max have to be positive values".to_string(),
+            node_id: node_id.to_string(),
+        })?;
+    }
+
+    let mut map = map.cached();
+
+    let ways =
+        collection
+            .features
+            .iter()
+            .filter_map(|w| match w.geometry.as_ref().map(|g| &g.value) {
+                Some(Value::LineString(coords)) => Some((w, coords)),
+                _ => None,
+            });
+
+    let features = ways
+        .flat_map(|(way, coords)| {
+            let elevations = coords
+                .iter()
+                .map(|c| map.lookup(c[0], c[1]))
+                .collect::<Vec<_>>();
+
+            // runs of consecutive matching segments, as indices into coords
+            let mut runs: Vec<(usize, usize)> = vec![];
+            for i in 0..coords.len().saturating_sub(1) {
+                let (Some(e1), Some(e2)) = (elevations[i], elevations[i + 1]) else {
+                    continue;
+                };
+                let Some(grade) = grade(&coords[i], e1, &coords[i + 1], e2) else {
+                    continue;
+                };
+                if grade < min || max < grade {
+                    continue;
+                }
+
+                match runs.last_mut() {
+                    Some((_, end)) if merge && *end == i => *end = i + 1,
+                    _ => runs.push((i, i + 1)),
+                }
+            }
+
+            runs.into_iter()
+                .map(|(start, end)| Feature {
+                    id: way.id.clone().and_then(|id| new_id(id, SLOPE_NUMBER)),
+                    geometry: Some(Value::LineString(coords[start..=end].to_vec()).into()),
+                    properties: way.properties.clone(),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    tracing::trace!("there were {} cached datasets", map.cache.len());
+
+    Ok(FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    })
+}
+
+/// absolute grade in percent between two coordinates, or `None` if they are in the same place
+fn grade(from: &[f64], from_elevation: f64, to: &[f64], to_elevation: f64) -> Option<f64> {
+    let distance = Point::new(from[0], from[1]).geodesic_distance(&Point::new(to[0], to[1]));
+    if distance == 0.0 {
+        return None;
+    }
+
+    Some((to_elevation - from_elevation).abs() / distance * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grade() {
+        // one degree of latitude is around 111km
+        let g = grade(&[0.0, 0.0], 0.0, &[0.0, 0.01], 111.0).unwrap();
+        assert!((g - 10.0).abs() < 0.1);
+
+        let g = grade(&[0.0, 0.01], 111.0, &[0.0, 0.0], 0.0).unwrap();
+        assert!((g - 10.0).abs() < 0.1);
+
+        assert!(grade(&[1.0, 1.0], 0.0, &[1.0, 1.0], 10.0).is_none());
+    }
+}
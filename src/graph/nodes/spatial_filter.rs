@@ -0,0 +1,226 @@
+use geo::{
+    BoundingRect, Closest, ClosestPoint, Contains, CoordsIter, GeodesicDistance, Geometry,
+    Intersects as _, Point,
+};
+use geojson::{Feature, FeatureCollection};
+use rtree_rs::{RTree, Rect};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::{NodeOutputs, DEFAULT_OUTPUT},
+    process::NodeProcessor,
+    utils::expand_rect,
+    Control,
+};
+
+/// Keeps the `in` features that are within a distance of any `aux` feature
+#[derive(Deserialize, Debug)]
+pub struct WithinDistance {
+    /// in meters
+    distance: Control<f64>,
+}
+
+/// Keeps the `in` features that intersect any `aux` feature
+#[derive(Deserialize, Debug)]
+pub struct Intersects;
+
+/// Keeps the `in` features that are inside any `aux` feature
+#[derive(Deserialize, Debug)]
+pub struct Inside;
+
+#[derive(Debug, Clone, Copy)]
+enum Relation {
+    WithinDistance(f64),
+    Intersects,
+    Inside,
+}
+
+#[async_trait::async_trait]
+impl Node for WithinDistance {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        // TODO add client-side validation too
+        let distance = self.distance.value;
+        if distance < 0.0 {
+            Err(GraphError::Spatial {
+                message: "Distance has to be a positive value".to_string(),
+                node_id: node_id.to_string(),
+            })?;
+        }
+
+        process(processor, node_id, Relation::WithinDistance(distance)).await
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Intersects {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        process(processor, node_id, Relation::Intersects).await
+    }
+}
+
+#[async_trait::async_trait]
+impl Node for Inside {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        process(processor, node_id, Relation::Inside).await
+    }
+}
+
+async fn process(
+    processor: &NodeProcessor<'_>,
+    node_id: &str,
+    relation: Relation,
+) -> Result<NodeOutputs, GraphError> {
+    let (input, aux) = tokio::try_join!(
+        processor.get_input(node_id, "in"),
+        processor.get_input(node_id, "aux")
+    )?;
+    let collection = input.into_features()?;
+    let aux = aux.into_features()?;
+
+    // geometry operations over large collections are cpu heavy, so they run outside of the async runtime
+    let (matching, rejected) =
+        tokio::task::spawn_blocking(move || filter(collection, &aux, relation)).await?;
+
+    Ok(NodeOutputs::new()
+        .with(DEFAULT_OUTPUT, matching)
+        .with("rejected", rejected))
+}
+
+fn filter(
+    collection: FeatureCollection,
+    aux: &FeatureCollection,
+    relation: Relation,
+) -> (FeatureCollection, FeatureCollection) {
+    let _span = tracing::trace_span!("spatial_filter::filter");
+    let _span = _span.enter();
+
+    let index = AuxIndex::new(aux);
+
+    let (matching, rejected): (Vec<_>, Vec<_>) =
+        collection.features.into_iter().partition(|feature| {
+            let Some(geometry) = geometry(feature) else {
+                return false;
+            };
+            let Some(rect) = geometry.bounding_rect() else {
+                return false;
+            };
+
+            match relation {
+                Relation::WithinDistance(distance) => index
+                    .candidates(expand_rect(rect, distance))
+                    .any(|aux| geodesic_distance(&geometry, aux) <= distance),
+                Relation::Intersects => index.candidates(rect).any(|aux| aux.intersects(&geometry)),
+                Relation::Inside => index.candidates(rect).any(|aux| aux.contains(&geometry)),
+            }
+        });
+
+    (
+        FeatureCollection {
+            bbox: None,
+            features: matching,
+            foreign_members: None,
+        },
+        FeatureCollection {
+            bbox: None,
+            features: rejected,
+            foreign_members: None,
+        },
+    )
+}
+
+/// R-tree of the geometries of the `aux` features, by their bounding box
+struct AuxIndex {
+    geometries: Vec<Geometry>,
+    tree: RTree<2, f64, usize>,
+}
+
+impl AuxIndex {
+    fn new(aux: &FeatureCollection) -> Self {
+        let geometries = aux.features.iter().filter_map(geometry).collect::<Vec<_>>();
+
+        let mut tree = RTree::new();
+        for (i, g) in geometries.iter().enumerate() {
+            if let Some(rect) = g.bounding_rect() {
+                tree.insert(to_rtree_rect(rect), i);
+            }
+        }
+
+        Self { geometries, tree }
+    }
+
+    /// geometries whose bounding box intersects `rect`
+    fn candidates(&self, rect: geo::Rect) -> impl Iterator<Item = &Geometry> {
+        self.tree
+            .search(to_rtree_rect(rect))
+            .map(|item| &self.geometries[*item.data])
+    }
+}
+
+fn geometry(feature: &Feature) -> Option<Geometry> {
+    let geometry = feature.geometry.as_ref()?;
+    (&geometry.value).try_into().ok()
+}
+
+fn to_rtree_rect(rect: geo::Rect) -> Rect<2, f64> {
+    Rect::new([rect.min().x, rect.min().y], [rect.max().x, rect.max().y])
+}
+
+/// distance in meters between the closest points of two geometries.
+///
+/// the closest points are found in degrees, which is close enough for the short distances we work with
+fn geodesic_distance(a: &Geometry, b: &Geometry) -> f64 {
+    if a.intersects(b) {
+        return 0.0;
+    }
+
+    let closest = |from: &Geometry, to: &Geometry| {
+        from.coords_iter()
+            .filter_map(|c| match to.closest_point(&c.into()) {
+                Closest::Intersection(p) | Closest::SinglePoint(p) => {
+                    Some(Point::from(c).geodesic_distance(&p))
+                }
+                Closest::Indeterminate => None,
+            })
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    closest(a, b).min(closest(b, a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::{line_string, point, polygon};
+
+    #[test]
+    fn test_geodesic_distance() {
+        let p: Geometry = point!(x: 0.0, y: 0.0).into();
+        let line: Geometry = line_string![(x: -1.0, y: 0.001), (x: 1.0, y: 0.001)].into();
+        let square: Geometry = polygon![
+            (x: -1.0, y: -1.0),
+            (x: 1.0, y: -1.0),
+            (x: 1.0, y: 1.0),
+            (x: -1.0, y: 1.0),
+        ]
+        .into();
+
+        // 0.001 degrees of latitude is around 110m
+        assert!((geodesic_distance(&p, &line) - 110.6).abs() < 1.0);
+        assert!((geodesic_distance(&line, &p) - 110.6).abs() < 1.0);
+        assert_eq!(geodesic_distance(&p, &square), 0.0);
+    }
+}
@@ -0,0 +1,340 @@
+use std::{iter::Peekable, str::Chars};
+
+use geojson::{Feature, FeatureCollection, JsonValue};
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::{NodeOutputs, DEFAULT_OUTPUT},
+    process::NodeProcessor,
+    Control,
+};
+
+#[derive(Deserialize, Debug)]
+pub struct TagFilter {
+    /// Conditions in overpass syntax, like `[highway=residential][!name][maxspeed>30]`.
+    /// Features have to match all of them
+    filter: Control<String>,
+}
+
+#[async_trait::async_trait]
+impl Node for TagFilter {
+    async fn process(
+        &self,
+        processor: &NodeProcessor<'_>,
+        node_id: &str,
+    ) -> Result<NodeOutputs, GraphError> {
+        let collection = processor.get_input(node_id, "in").await?.into_features()?;
+
+        // TODO add client-side validation too
+        let conditions = parse(&self.filter.value).map_err(|message| GraphError::TagFilter {
+            message,
+            node_id: node_id.to_string(),
+        })?;
+
+        let (matching, rejected): (Vec<_>, Vec<_>) = collection
+            .features
+            .into_iter()
+            .partition(|feature| conditions.iter().all(|c| c.matches(feature)));
+
+        Ok(NodeOutputs::new()
+            .with(
+                DEFAULT_OUTPUT,
+                FeatureCollection {
+                    bbox: None,
+                    features: matching,
+                    foreign_members: None,
+                },
+            )
+            .with(
+                "rejected",
+                FeatureCollection {
+                    bbox: None,
+                    features: rejected,
+                    foreign_members: None,
+                },
+            ))
+    }
+}
+
+#[derive(Debug)]
+enum Condition {
+    /// `[key]`
+    Exists(String),
+    /// `[!key]`
+    NotExists(String),
+    /// `[key=value]`
+    Equals(String, String),
+    /// `[key!=value]`, which also matches features without `key`
+    NotEquals(String, String),
+    /// `[key~regex]`, or `[key~"regex",i]` to ignore case
+    Matches(String, Regex),
+    /// `[key!~regex]`, which also matches features without `key`
+    NotMatches(String, Regex),
+    /// `[key<number]`, `[key<=number]`, `[key>number]` or `[key>=number]`.
+    /// only matches values that start with a number, so `50 mph` is 50
+    Compare(String, Comparison, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Condition {
+    fn matches(&self, feature: &Feature) -> bool {
+        let value = |key: &str| {
+            feature
+                .properties
+                .as_ref()
+                .and_then(|p| p.get(key))
+                .and_then(value_to_string)
+        };
+
+        match self {
+            Self::Exists(key) => value(key).is_some(),
+            Self::NotExists(key) => value(key).is_none(),
+            Self::Equals(key, expected) => value(key).is_some_and(|v| v == *expected),
+            Self::NotEquals(key, expected) => value(key).is_none_or(|v| v != *expected),
+            Self::Matches(key, regex) => value(key).is_some_and(|v| regex.is_match(&v)),
+            Self::NotMatches(key, regex) => value(key).is_none_or(|v| !regex.is_match(&v)),
+            Self::Compare(key, comparison, expected) => value(key)
+                .and_then(|v| leading_number(&v))
+                .is_some_and(|v| match comparison {
+                    Comparison::Lt => v < *expected,
+                    Comparison::Le => v <= *expected,
+                    Comparison::Gt => v > *expected,
+                    Comparison::Ge => v >= *expected,
+                }),
+        }
+    }
+}
+
+/// property value as a tag value, `None` if it's missing
+fn value_to_string(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::Null => None,
+        JsonValue::String(s) => Some(s.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+/// parses the number at the start of `value`, ignoring whitespace and units after it
+fn leading_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let end = value
+        .char_indices()
+        .find(|(i, c)| !(c.is_ascii_digit() || *c == '.' || (*i == 0 && (*c == '-' || *c == '+'))))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].parse().ok()
+}
+
+fn parse(filter: &str) -> Result<Vec<Condition>, String> {
+    let mut chars = filter.chars().peekable();
+    let mut conditions = vec![];
+
+    loop {
+        skip_whitespace(&mut chars);
+        match chars.next() {
+            None => break,
+            Some('[') => conditions.push(parse_condition(&mut chars)?),
+            Some(c) => return Err(format!("expected [, found {c}")),
+        }
+    }
+
+    Ok(conditions)
+}
+
+/// parses a condition, after its opening `[`
+fn parse_condition(chars: &mut Peekable<Chars>) -> Result<Condition, String> {
+    skip_whitespace(chars);
+    let negated = chars.next_if_eq(&'!').is_some();
+
+    let key = parse_string(chars, |c| matches!(c, '=' | '!' | '~' | '<' | '>' | ']'))?;
+    if key.is_empty() {
+        return Err("expected a key".to_string());
+    }
+    skip_whitespace(chars);
+
+    if negated {
+        expect(chars, ']')?;
+        return Ok(Condition::NotExists(key));
+    }
+
+    let operator = match chars.next() {
+        Some(']') => return Ok(Condition::Exists(key)),
+        Some('!') => match chars.next() {
+            Some('=') => "!=",
+            Some('~') => "!~",
+            _ => return Err(format!("expected != or !~ after {key}")),
+        },
+        Some('=') => "=",
+        Some('~') => "~",
+        Some('<') if chars.next_if_eq(&'=').is_some() => "<=",
+        Some('<') => "<",
+        Some('>') if chars.next_if_eq(&'=').is_some() => ">=",
+        Some('>') => ">",
+        Some(c) => return Err(format!("unexpected {c} after {key}")),
+        None => return Err(format!("unclosed condition for {key}")),
+    };
+
+    skip_whitespace(chars);
+    let quoted = chars.peek() == Some(&'"');
+    let mut value = parse_string(chars, |c| c == ']')?;
+    skip_whitespace(chars);
+
+    // `,i` after a regex makes it case insensitive
+    let mut case_insensitive = false;
+    if chars.next_if_eq(&',').is_some() {
+        skip_whitespace(chars);
+        expect(chars, 'i')?;
+        skip_whitespace(chars);
+        case_insensitive = true;
+    } else if !quoted && operator.contains('~') {
+        if let Some(v) = value.strip_suffix(",i") {
+            value = v.trim_end().to_string();
+            case_insensitive = true;
+        }
+    }
+    expect(chars, ']')?;
+
+    if case_insensitive && !operator.contains('~') {
+        return Err(format!("only regexes can be case insensitive, in {key}"));
+    }
+
+    let regex = || {
+        RegexBuilder::new(&value)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|err| format!("invalid regex for {key}: {err}"))
+    };
+    let number = || {
+        value
+            .parse::<f64>()
+            .map_err(|_| format!("expected a number for {key}, found {value}"))
+    };
+
+    Ok(match operator {
+        "=" => Condition::Equals(key, value),
+        "!=" => Condition::NotEquals(key, value),
+        "~" => Condition::Matches(key.clone(), regex()?),
+        "!~" => Condition::NotMatches(key.clone(), regex()?),
+        "<" => Condition::Compare(key.clone(), Comparison::Lt, number()?),
+        "<=" => Condition::Compare(key.clone(), Comparison::Le, number()?),
+        ">" => Condition::Compare(key.clone(), Comparison::Gt, number()?),
+        _ => Condition::Compare(key.clone(), Comparison::Ge, number()?),
+    })
+}
+
+/// parses a quoted string, or an unquoted one until `is_end` matches, trimming it.
+/// in quoted strings only `\"` and `\\` are unescaped, so regexes keep their escapes like `\d`
+fn parse_string(
+    chars: &mut Peekable<Chars>,
+    is_end: impl Fn(char) -> bool,
+) -> Result<String, String> {
+    skip_whitespace(chars);
+    let mut s = String::new();
+
+    if chars.next_if_eq(&'"').is_some() {
+        loop {
+            match chars.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match chars.next() {
+                    Some(c @ ('"' | '\\')) => s.push(c),
+                    Some(c) => {
+                        s.push('\\');
+                        s.push(c);
+                    }
+                    None => break,
+                },
+                Some(c) => s.push(c),
+                None => break,
+            }
+        }
+        return Err(format!("unclosed quote in \"{s}"));
+    }
+
+    while let Some(c) = chars.next_if(|c| !is_end(*c)) {
+        s.push(c);
+    }
+    Ok(s.trim().to_string())
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("expected {expected}, found {c}")),
+        None => Err(format!("expected {expected}")),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feature(properties: JsonValue) -> Feature {
+        Feature {
+            properties: properties.as_object().cloned(),
+            ..Default::default()
+        }
+    }
+
+    fn matches(filter: &str, feature: &Feature) -> bool {
+        parse(filter).unwrap().iter().all(|c| c.matches(feature))
+    }
+
+    #[test]
+    fn test_conditions() {
+        let road = feature(serde_json::json!({
+            "highway": "residential",
+            "name": "Carrer Gran",
+            "maxspeed": "30 mph",
+            "lanes": 2,
+        }));
+
+        assert!(matches("[highway=residential][name]", &road));
+        assert!(matches("[!ref] [highway != primary]", &road));
+        assert!(matches(r#"[name~"^carrer",i]"#, &road));
+        assert!(!matches("[name~^carrer]", &road));
+        assert!(matches("[name!~Avinguda]", &road));
+        assert!(matches("[maxspeed>=30][maxspeed<31][lanes>1]", &road));
+        assert!(!matches("[lanes>2]", &road));
+        assert!(matches("", &road));
+        assert!(matches(r#"["highway"="residential"]"#, &road));
+    }
+
+    #[test]
+    fn test_quoted_regex() {
+        let number = feature(serde_json::json!({ "ref": "12" }));
+        let letters = feature(serde_json::json!({ "ref": "dd" }));
+
+        assert!(matches(r#"[ref~"^\d+$"]"#, &number));
+        assert!(!matches(r#"[ref~"^\d+$"]"#, &letters));
+        // `.` only matches a dot when escaped
+        let dotted = feature(serde_json::json!({ "ref": "1x2" }));
+        assert!(!matches(r#"[ref~"^1\.2$"]"#, &dotted));
+        assert!(matches(r#"[ref~"^(12|\])$"]"#, &number));
+        assert!(matches(
+            r#"[ref="1\"2"]"#,
+            &feature(serde_json::json!({ "ref": "1\"2" }))
+        ));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("highway=residential").is_err());
+        assert!(parse("[highway=residential").is_err());
+        assert!(parse("[lanes>two]").is_err());
+        assert!(parse("[name~(]").is_err());
+        assert!(parse("[=residential]").is_err());
+    }
+}
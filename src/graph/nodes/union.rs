@@ -1,6 +1,15 @@
+use std::collections::HashSet;
+
+use geojson::Feature;
 use serde::Deserialize;
 
-use crate::graph::{errors::GraphError, nodes::Node, output::NodeOutput, process::NodeProcessor};
+use crate::graph::{
+    errors::GraphError,
+    nodes::Node,
+    output::NodeOutputs,
+    process::NodeProcessor,
+    utils::{geometry_key, osm_identity},
+};
 
 #[derive(Deserialize, Debug)]
 pub struct Union;
@@ -9,13 +18,53 @@ pub struct Union;
 impl Node for Union {
     async fn process(
         &self,
-        processor: &mut NodeProcessor<'_>,
+        processor: &NodeProcessor<'_>,
         node_id: &str,
-    ) -> Result<NodeOutput, GraphError> {
-        let mut a_collection = processor.get_input(node_id, "a").await?.into_features()?;
-        let b_collection = processor.get_input(node_id, "b").await?.into_features()?;
+    ) -> Result<NodeOutputs, GraphError> {
+        let (a, b) = tokio::try_join!(
+            processor.get_input(node_id, "a"),
+            processor.get_input(node_id, "b")
+        )?;
+        let mut a_collection = a.into_features()?;
+        let b_collection = b.into_features()?;
 
         a_collection.features.extend(b_collection.features);
+        dedup(&mut a_collection.features);
+
         Ok(a_collection.into())
     }
 }
+
+/// drops features that are in both inputs. segments of the same way have the same osm identity,
+/// so the geometry has to match too
+fn dedup(features: &mut Vec<Feature>) {
+    let mut seen = HashSet::new();
+    features.retain(|f| {
+        let key = (osm_identity(f), geometry_key(f));
+        key == (None, None) || seen.insert(key)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup() {
+        let segment = |coords: serde_json::Value| -> Feature {
+            serde_json::from_value(serde_json::json!({
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": coords },
+                "properties": { "osm_type": "way", "osm_id": 1 },
+            }))
+            .unwrap()
+        };
+        let first = segment(serde_json::json!([[0.0, 0.0], [1.0, 0.0]]));
+        let second = segment(serde_json::json!([[1.0, 0.0], [2.0, 0.0]]));
+
+        // both segments of the split way are kept, and the copy of the first one is dropped
+        let mut features = vec![first.clone(), second.clone(), first.clone()];
+        dedup(&mut features);
+        assert_eq!(features, vec![first, second]);
+    }
+}
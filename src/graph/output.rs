@@ -1,12 +1,24 @@
+use std::collections::BTreeMap;
+
 use crate::graph::errors::GraphError;
 use geojson::FeatureCollection;
 
+/// Name of the output of nodes that only have one
+pub const DEFAULT_OUTPUT: &str = "out";
+
 #[derive(Clone)]
 pub enum NodeOutput {
     Features(FeatureCollection),
     Query(String),
 }
 impl NodeOutput {
+    /// returns the amount of features, if this output is geojson
+    pub fn feature_count(&self) -> Option<usize> {
+        match self {
+            NodeOutput::Features(collection) => Some(collection.features.len()),
+            NodeOutput::Query(_) => None,
+        }
+    }
     pub fn into_features(self) -> Result<FeatureCollection, GraphError> {
         if let NodeOutput::Features(val) = self {
             Ok(val)
@@ -38,3 +50,44 @@ impl From<String> for NodeOutput {
         Self::Query(value)
     }
 }
+
+/// All the outputs of a node, by the name of the output
+///
+/// Nodes with a single output can use `.into()`, which uses [`DEFAULT_OUTPUT`] as the name
+#[derive(Clone, Default)]
+pub struct NodeOutputs(BTreeMap<String, NodeOutput>);
+impl NodeOutputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// adds an output with the given name
+    pub fn with(mut self, name: &str, output: impl Into<NodeOutput>) -> Self {
+        self.0.insert(name.to_string(), output.into());
+        self
+    }
+    pub fn get(&self, name: &str) -> Option<&NodeOutput> {
+        self.0.get(name)
+    }
+    /// returns the total amount of features, if any of the outputs is geojson
+    pub fn feature_count(&self) -> Option<usize> {
+        self.0
+            .values()
+            .filter_map(NodeOutput::feature_count)
+            .reduce(|a, b| a + b)
+    }
+}
+impl From<NodeOutput> for NodeOutputs {
+    fn from(value: NodeOutput) -> Self {
+        Self::new().with(DEFAULT_OUTPUT, value)
+    }
+}
+impl From<FeatureCollection> for NodeOutputs {
+    fn from(value: FeatureCollection) -> Self {
+        NodeOutput::from(value).into()
+    }
+}
+impl From<String> for NodeOutputs {
+    fn from(value: String) -> Self {
+        NodeOutput::from(value).into()
+    }
+}
@@ -1,43 +1,55 @@
-use std::collections::{BTreeMap, HashMap};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
 
+use futures_util::future::try_join_all;
 use geojson::FeatureCollection;
+use serde::Serialize;
+use tokio::sync::{mpsc::UnboundedSender, OnceCell};
 use tracing::Instrument;
 
 use crate::{
     cache::Caches,
     elevation::ElevationMap,
     graph::{
-        errors::GraphError, output::NodeOutput, utils::detect_cycles, Graph, GraphConnection,
-        GraphNode,
+        errors::GraphError,
+        map_nodes,
+        output::{NodeOutput, NodeOutputs},
+        utils::detect_cycles,
+        Graph, GraphConnection, GraphNode,
     },
+    overpass::OverpassClient,
     search::{Bbox, GeocodeaArea, SearchError},
 };
 
 pub struct ProcessResult {
-    pub collection: FeatureCollection,
+    /// One layer for every Map node that has an input
+    pub layers: Vec<Layer>,
     pub geocode_areas: Vec<GeocodeaArea>,
     pub processed_queries: HashMap<String, String>,
+    pub endpoints: HashMap<String, String>,
 }
 
-impl Default for ProcessResult {
-    fn default() -> Self {
-        Self {
-            collection: FeatureCollection {
-                bbox: None,
-                features: vec![],
-                foreign_members: None,
-            },
-            geocode_areas: Default::default(),
-            processed_queries: Default::default(),
-        }
-    }
+/// The results that arrive at a Map node
+#[derive(Serialize, Debug)]
+pub struct Layer {
+    pub name: String,
+    /// Color to display the layer with, as a css color
+    pub color: Option<String>,
+    pub data: FeatureCollection,
 }
 
 pub async fn process_graph(
     graph: Graph,
     bbox: Bbox,
-    elevation_map: &ElevationMap,
+    elevation_map: Arc<ElevationMap>,
     caches: Caches,
+    overpass: &OverpassClient,
+    events: Option<UnboundedSender<NodeEvent>>,
 ) -> Result<ProcessResult, SearchError> {
     if detect_cycles(&graph.connections) {
         Err(GraphError::Cycle)?;
@@ -45,53 +57,125 @@ pub async fn process_graph(
 
     let nodes = BTreeMap::from_iter(graph.nodes.iter().map(|n| (n.id.as_str(), n)));
 
-    let map_id = &graph.map_node()?.id;
+    let maps = map_nodes(&graph.nodes)?;
 
-    let Some(con) = graph.connections.iter().find(|c| c.target == *map_id) else {
-        return Ok(ProcessResult::default());
-    };
-    let prev = nodes
-        .get(con.source.as_str())
-        .ok_or(GraphError::ConnectionNodeMissing)?;
-
-    let mut np = NodeProcessor {
+    let np = NodeProcessor {
         nodes: &nodes,
         connections: graph.connections,
         bbox,
-        geocode_areas: vec![],
+        geocode_areas: Default::default(),
         processed_queries: Default::default(),
+        endpoints: Default::default(),
         memory: Default::default(),
+        events,
+        errored: Default::default(),
 
         elevation_map,
         caches,
+        overpass,
     };
 
-    let collection = np.process_node(prev).await?.into_features()?;
+    // every map gets processed concurrently, sharing the nodes they have in common
+    let layers = try_join_all(
+        maps.into_iter()
+            .enumerate()
+            .filter_map(|(i, (node, map))| {
+                let con = np.connections.iter().find(|c| c.target == node.id)?;
+                Some((i, map, con))
+            })
+            .map(|(i, map, con)| {
+                let np = &np;
+                async move {
+                    let data = np.process_connection(con).await?.into_features()?;
+                    Ok::<_, GraphError>(Layer {
+                        name: map
+                            .layer_name()
+                            .map(ToString::to_string)
+                            .unwrap_or_else(|| format!("Layer {}", i + 1)),
+                        color: map.color().map(ToString::to_string),
+                        data,
+                    })
+                }
+            }),
+    )
+    .await?;
 
     Ok(ProcessResult {
-        collection,
-        geocode_areas: np.geocode_areas,
-        processed_queries: np.processed_queries,
+        layers,
+        geocode_areas: np.geocode_areas.into_inner().unwrap(),
+        processed_queries: np.processed_queries.into_inner().unwrap(),
+        endpoints: np.endpoints.into_inner().unwrap(),
     })
 }
 
+/// Processes the nodes of a graph
+///
+/// Nodes only get `&NodeProcessor`, so independent inputs can be processed concurrently
+/// by awaiting them together, eg with `tokio::try_join!`
 pub struct NodeProcessor<'a> {
     // i dont think these two lifetimes are the same but meh
     nodes: &'a BTreeMap<&'a str, &'a GraphNode>,
     connections: Vec<GraphConnection>,
     pub bbox: Bbox,
-    pub geocode_areas: Vec<GeocodeaArea>,
-    pub processed_queries: HashMap<String, String>,
-    memory: HashMap<String, NodeOutput>,
+    pub geocode_areas: Mutex<Vec<GeocodeaArea>>,
+    pub processed_queries: Mutex<HashMap<String, String>>,
+    /// Node Id -> Overpass endpoint used
+    pub endpoints: Mutex<HashMap<String, String>>,
+    /// Node Id -> Outputs of the node
+    ///
+    /// Every node gets a cell, so nodes that are inputs to several others are only processed once,
+    /// even when they are requested concurrently
+    memory: Mutex<HashMap<String, Arc<OnceCell<NodeOutputs>>>>,
+    events: Option<UnboundedSender<NodeEvent>>,
+    /// whether an error event has already been sent
+    errored: AtomicBool,
 
-    pub elevation_map: &'a ElevationMap,
+    pub elevation_map: Arc<ElevationMap>,
     pub caches: Caches,
+    pub overpass: &'a OverpassClient,
 }
 
-// NOTE: this whole thing assumes every node has only one type of output
-// it will need adapting to support multiple outputs
+/// Progress of the processing of a node
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeEvent {
+    Started {
+        node_id: String,
+    },
+    /// The node's results were found in the cache, so nothing was fetched
+    CacheHit {
+        node_id: String,
+    },
+    Finished {
+        node_id: String,
+        /// Amount of features, if the node outputs geojson
+        feature_count: Option<usize>,
+    },
+    Error {
+        node_id: String,
+        error: String,
+    },
+}
 
 impl<'a> NodeProcessor<'a> {
+    /// sends an event to whoever is listening to the progress, if anyone
+    pub fn emit(&self, event: NodeEvent) {
+        if let Some(events) = &self.events {
+            // the receiver is dropped if the client disconnected, in which case we don't care
+            let _ = events.send(event);
+        }
+    }
+
+    /// runs `f` on a blocking thread, since elevation lookups do blocking io
+    pub async fn with_elevation<T, F>(&self, f: F) -> Result<T, GraphError>
+    where
+        F: FnOnce(&ElevationMap) -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let map = self.elevation_map.clone();
+        Ok(tokio::task::spawn_blocking(move || f(&map)).await?)
+    }
+
     /// find a connection that targets `n` on the `target` input
     fn find_connection(&self, node_id: &str, target: &str) -> Result<&GraphConnection, GraphError> {
         self.connections
@@ -108,28 +192,76 @@ impl<'a> NodeProcessor<'a> {
     }
 
     /// get and compute the node connected to input `name`
-    pub async fn get_input(&mut self, node_id: &str, name: &str) -> Result<NodeOutput, GraphError> {
+    pub async fn get_input(&self, node_id: &str, name: &str) -> Result<NodeOutput, GraphError> {
         let con = self.find_connection(node_id, name)?;
+        self.process_connection(con).await
+    }
+
+    /// compute the source node of `con`, and get the output the connection comes from
+    async fn process_connection(&self, con: &GraphConnection) -> Result<NodeOutput, GraphError> {
         let prev = self.get_node(&con.source)?;
-        self.process_node(prev).await
+        let outputs = self.process_node(prev).await?;
+
+        outputs
+            .get(&con.source_output)
+            .cloned()
+            .ok_or_else(|| GraphError::OutputMissing {
+                node_id: con.source.clone(),
+                output: con.source_output.clone(),
+            })
     }
 
     #[async_recursion::async_recursion]
-    async fn process_node(&mut self, node: &GraphNode) -> Result<NodeOutput, GraphError> {
+    async fn process_node(&self, node: &GraphNode) -> Result<NodeOutputs, GraphError> {
         let span = tracing::debug_span!("process_node", node_id = &node.id);
 
-        if let Some(res) = self.memory.get(&node.id) {
+        let cell = self
+            .memory
+            .lock()
+            .unwrap()
+            .entry(node.id.clone())
+            .or_default()
+            .clone();
+
+        if let Some(res) = cell.get() {
             span.in_scope(|| tracing::debug!("node was in memory, skipping processing"));
             return Ok(res.clone());
         }
 
-        span.in_scope(|| tracing::debug!("node is not in memory, beginning processing"));
+        // if another branch is already processing this node, this waits for it to finish
+        let res = cell
+            .get_or_try_init(|| async {
+                span.in_scope(|| tracing::debug!("node is not in memory, beginning processing"));
 
-        // TODO maybe we can store the current id in the struct so we can use it from get_input?
-        let res = node.process(self).instrument(span).await?;
+                self.emit(NodeEvent::Started {
+                    node_id: node.id.clone(),
+                });
 
-        self.memory.insert(node.id.clone(), res.clone());
+                // TODO maybe we can store the current id in the struct so we can use it from get_input?
+                let res = match node.process(self).instrument(span.clone()).await {
+                    Ok(res) => res,
+                    Err(err) => {
+                        // errors bubble up through every node that depends on this one,
+                        // so we only report the node where it happened
+                        if !self.errored.swap(true, Ordering::Relaxed) {
+                            self.emit(NodeEvent::Error {
+                                node_id: node.id.clone(),
+                                error: err.to_string(),
+                            });
+                        }
+                        return Err(err);
+                    }
+                };
+
+                self.emit(NodeEvent::Finished {
+                    node_id: node.id.clone(),
+                    feature_count: res.feature_count(),
+                });
+
+                Ok(res)
+            })
+            .await?;
 
-        Ok(res)
+        Ok(res.clone())
     }
 }
@@ -1,4 +1,7 @@
-use geojson::feature::Id;
+use std::collections::HashSet;
+
+use geojson::{feature::Id, Feature, FeatureCollection, Value};
+use serde::Deserialize;
 
 use super::GraphConnection;
 
@@ -24,6 +27,9 @@ pub fn detect_cycles(connections: &[GraphConnection]) -> bool {
 
 pub const RLF_NUMBER: u64 = 1000000000000000;
 pub const RAF_NUMBER: u64 = 2000000000000000;
+pub const SLOPE_NUMBER: u64 = 3000000000000000;
+pub const JUNCTION_NUMBER: u64 = 4000000000000000;
+pub const MERGE_NUMBER: u64 = 5000000000000000;
 pub fn new_id(id: Id, number: u64) -> Option<Id> {
     match id {
         Id::Number(n) if n.is_u64() => Some(Id::Number((n.as_u64().unwrap() + number).into())),
@@ -31,6 +37,104 @@ pub fn new_id(id: Id, number: u64) -> Option<Id> {
     }
 }
 
+/// `osm_type/osm_id` of a feature, from the properties set when converting osm elements to geojson
+pub fn osm_identity(feature: &Feature) -> Option<String> {
+    let properties = feature.properties.as_ref()?;
+    let osm_type = properties.get("osm_type")?.as_str()?;
+    let osm_id = properties.get("osm_id")?;
+    Some(format!("{osm_type}/{osm_id}"))
+}
+
+/// geometry of a feature serialized, to compare geometries exactly
+pub fn geometry_key(feature: &Feature) -> Option<String> {
+    let geometry = feature.geometry.as_ref()?;
+    serde_json::to_string(&geometry.value).ok()
+}
+
+/// How set operations on geojson decide whether two features are the same
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FeatureIdentity {
+    /// same `osm_type` and `osm_id`, falling back to the geometry for features without them
+    #[default]
+    Osm,
+    /// exactly the same geometry
+    Geometry,
+}
+
+impl FeatureIdentity {
+    pub fn key(self, feature: &Feature) -> Option<String> {
+        match self {
+            Self::Osm => osm_identity(feature).or_else(|| geometry_key(feature)),
+            Self::Geometry => geometry_key(feature),
+        }
+    }
+}
+
+/// Keeps the features of `a` that are in `b` when `keep_present` is set, or the ones that aren't otherwise
+pub fn retain_by_identity(
+    a: &mut FeatureCollection,
+    b: &FeatureCollection,
+    identity: FeatureIdentity,
+    keep_present: bool,
+) {
+    let b_keys = b
+        .features
+        .iter()
+        .filter_map(|f| identity.key(f))
+        .collect::<HashSet<_>>();
+
+    a.features.retain(|f| {
+        let present = identity.key(f).is_some_and(|key| b_keys.contains(&key));
+        present == keep_present
+    });
+}
+
+/// Converts the geometry of every feature with `f`, keeping ids and properties.
+/// Features without a geometry, or that `f` returns `None` for, are dropped
+pub fn map_geometries(
+    collection: FeatureCollection,
+    mut f: impl FnMut(geo::Geometry) -> Option<geo::Geometry>,
+) -> FeatureCollection {
+    let features = collection
+        .features
+        .into_iter()
+        .filter_map(|mut feature| {
+            let geometry = feature.geometry.as_ref()?;
+            let geometry = geo::Geometry::<f64>::try_from(&geometry.value).ok()?;
+            feature.geometry = Some(Value::from(&f(geometry)?).into());
+            Some(feature)
+        })
+        .collect();
+
+    FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    }
+}
+
+/// rounds to centimeters, computed distances and elevations have a lot of meaningless decimals
+pub fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Meters in a degree of latitude, used to turn distances into bounding boxes
+pub const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// grows `rect` by `meters` in every direction
+pub fn expand_rect(rect: geo::Rect, meters: f64) -> geo::Rect {
+    let lat_delta = meters / METERS_PER_DEGREE;
+    // degrees of longitude are shortest at the latitude furthest from the equator
+    let max_lat = (rect.min().y.abs().max(rect.max().y.abs()) + lat_delta).min(89.0);
+    let lng_delta = (meters / (METERS_PER_DEGREE * max_lat.to_radians().cos())).min(360.0);
+
+    geo::Rect::new(
+        (rect.min().x - lng_delta, rect.min().y - lat_delta),
+        (rect.max().x + lng_delta, rect.max().y + lat_delta),
+    )
+}
+
 /// returns angular distance between bearings
 ///
 /// return value is always positive, and less than 180
@@ -59,4 +163,128 @@ mod tests {
         assert!(t(2.0, bearing_distance(-1.0, 1.0)));
         assert!(t(3.0, bearing_distance(-179.0, 178.0)));
     }
+
+    /// two segments of way 1, split by a filter, way 2, and a feature that isn't from osm
+    fn features() -> Vec<Feature> {
+        serde_json::from_value(serde_json::json!([
+            {
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": [[0.0, 0.0], [1.0, 0.0]] },
+                "properties": { "osm_type": "way", "osm_id": 1 },
+            },
+            {
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": [[1.0, 0.0], [2.0, 0.0]] },
+                "properties": { "osm_type": "way", "osm_id": 1 },
+            },
+            {
+                "type": "Feature",
+                "geometry": { "type": "LineString", "coordinates": [[0.0, 1.0], [1.0, 1.0]] },
+                "properties": { "osm_type": "way", "osm_id": 2 },
+            },
+            {
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [0.0, 0.0] },
+                "properties": {},
+            },
+        ]))
+        .unwrap()
+    }
+
+    fn collection(features: Vec<Feature>) -> FeatureCollection {
+        FeatureCollection {
+            bbox: None,
+            features,
+            foreign_members: None,
+        }
+    }
+
+    #[test]
+    fn test_feature_identity() {
+        let features = features();
+
+        assert_eq!(
+            FeatureIdentity::Osm.key(&features[0]),
+            Some("way/1".to_string())
+        );
+        assert_eq!(
+            FeatureIdentity::Osm.key(&features[0]),
+            FeatureIdentity::Osm.key(&features[1])
+        );
+        assert_ne!(
+            FeatureIdentity::Geometry.key(&features[0]),
+            FeatureIdentity::Geometry.key(&features[1])
+        );
+        // features without osm properties fall back to their geometry
+        assert_eq!(
+            FeatureIdentity::Osm.key(&features[3]),
+            FeatureIdentity::Geometry.key(&features[3])
+        );
+    }
+
+    #[test]
+    fn test_retain_by_identity() {
+        let features = features();
+        let a = collection(vec![features[0].clone(), features[2].clone()]);
+        let b = collection(vec![features[1].clone()]);
+
+        let retained = |identity, keep_present| {
+            let mut a = a.clone();
+            retain_by_identity(&mut a, &b, identity, keep_present);
+            a.features
+                .iter()
+                .map(|f| f.property("osm_id").unwrap().clone())
+                .collect::<Vec<_>>()
+        };
+
+        // the other segment of way 1 is the same feature by osm identity, but not by geometry
+        assert_eq!(retained(FeatureIdentity::Osm, true), vec![1]);
+        assert_eq!(retained(FeatureIdentity::Osm, false), vec![2]);
+        assert!(retained(FeatureIdentity::Geometry, true).is_empty());
+        assert_eq!(retained(FeatureIdentity::Geometry, false), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_map_geometries() {
+        let collection: FeatureCollection = serde_json::from_value(serde_json::json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "Point", "coordinates": [2.0, 41.0] },
+                    "properties": { "name": "kept" },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": null,
+                    "properties": { "name": "without geometry" },
+                },
+                {
+                    "type": "Feature",
+                    "geometry": { "type": "LineString", "coordinates": [[2.0, 41.0], [3.0, 42.0]] },
+                    "properties": { "name": "dropped by f" },
+                },
+            ],
+        }))
+        .unwrap();
+
+        let res = map_geometries(collection, |g| match g {
+            geo::Geometry::Point(p) => Some(geo::Point::new(p.x() + 1.0, p.y()).into()),
+            _ => None,
+        });
+        assert_eq!(res.features.len(), 1);
+        assert_eq!(res.features[0].property("name"), Some(&"kept".into()));
+        assert_eq!(
+            res.features[0].geometry.as_ref().unwrap().value,
+            Value::Point(vec![3.0, 41.0])
+        );
+    }
+
+    #[test]
+    fn test_expand_rect() {
+        let rect = geo::Rect::new((0.0, 0.0), (0.0, 0.0));
+        let expanded = expand_rect(rect, METERS_PER_DEGREE);
+        assert!((expanded.max().y - 1.0).abs() < 1e-9);
+        assert!(expanded.max().x > 1.0);
+    }
 }
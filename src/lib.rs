@@ -1,12 +1,15 @@
 pub mod app_state;
+pub mod cache;
 pub mod elevation;
+pub mod elevation_api;
+pub mod export;
+pub mod graph;
+pub mod overpass;
 pub mod routes;
+pub mod search;
 pub mod taginfo;
 pub mod tracing;
 
-mod cache;
-mod graph;
 mod nominatim;
 mod osm_to_geojson;
 mod preprocess;
-mod search;
@@ -3,7 +3,7 @@ use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 use tokio::net::TcpListener;
 use tower_http::trace::TraceLayer;
 
-use underpass::{app_state, elevation, routes, taginfo::taginfo_path, tracing};
+use underpass::{app_state, elevation, overpass, routes, taginfo::taginfo_path, tracing};
 
 #[tokio::main]
 async fn main() {
@@ -18,17 +18,25 @@ async fn main() {
         .expect("failed to get DATA")
         .into();
 
-    let mut elevation_path = data_path.clone();
-    elevation_path.push("elevation");
+    let elevation_sources = elevation::elevation_sources(&data_path);
+    // in megabytes
+    let elevation_cache_size = std::env::var("ELEVATION_CACHE_SIZE")
+        .ok()
+        .and_then(|a| a.parse::<u64>().ok())
+        .map(|mb| mb * 1024 * 1024)
+        .unwrap_or(elevation::DEFAULT_CACHE_SIZE);
     let elevation_map =
-        elevation::ElevationMap::new(&elevation_path).expect("failed to load elevation map");
+        elevation::ElevationMap::from_sources(&elevation_sources, elevation_cache_size)
+            .expect("failed to load elevation map");
 
     let taginfo_path = taginfo_path(&data_path);
     if !taginfo_path.exists() {
         ::tracing::error!("{taginfo_path:?} not found");
     }
 
-    let state = app_state::AppState::new(data_path, elevation_map);
+    let overpass = overpass::OverpassClient::from_env();
+
+    let state = app_state::AppState::new(data_path, elevation_map, overpass);
 
     let app = routes::make_router()
         .layer(TraceLayer::new_for_http())
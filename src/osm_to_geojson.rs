@@ -1,5 +1,6 @@
 use std::collections::BTreeMap;
 
+use geo::Contains;
 use geojson::*;
 use serde::Deserialize;
 
@@ -20,10 +21,24 @@ pub fn osm_to_geojson(osm: Osm) -> FeatureCollection {
         }
     }));
 
+    let way_nodes = BTreeMap::from_iter(osm.elements.iter().filter_map(|n| {
+        if let Element::Way(way) = n {
+            Some((way.id, way.nodes.as_slice()))
+        } else {
+            None
+        }
+    }));
+
+    let maps = Maps {
+        node_map: &node_map,
+        way_map: &way_map,
+        way_nodes: &way_nodes,
+    };
+
     let features = osm
         .elements
-        .into_iter()
-        .flat_map(|el| element_to_feature(&el, &node_map, &way_map))
+        .iter()
+        .flat_map(|el| element_to_feature(el, &maps))
         .collect();
 
     FeatureCollection {
@@ -33,24 +48,25 @@ pub fn osm_to_geojson(osm: Osm) -> FeatureCollection {
     }
 }
 
+struct Maps<'a> {
+    /// Node id -> Coordinates
+    node_map: &'a BTreeMap<u64, Vec<f64>>,
+    /// Node id -> Id of a way containing it
+    way_map: &'a BTreeMap<u64, u64>,
+    /// Way id -> Node ids
+    way_nodes: &'a BTreeMap<u64, &'a [u64]>,
+}
+
 /// Convert an Osm element to a geojson feature
 ///
 /// Returns an option since areas are not converted to geojson
-fn element_to_feature(
-    el: &Element,
-    node_map: &BTreeMap<u64, Vec<f64>>,
-    way_map: &BTreeMap<u64, u64>,
-) -> Option<Feature> {
+fn element_to_feature(el: &Element, maps: &Maps) -> Option<Feature> {
     let mut feat = Feature {
         id: Some(feature::Id::Number(el.id().into())),
-        geometry: Some(element_to_geometry(el, node_map)?),
+        geometry: Some(element_to_geometry(el, maps)?),
         ..Default::default()
     };
 
-    // TODO probably change some lines to polygons
-    // > an heuristic has to be applied to determine whether a way is a Line or a Polygon
-    // https://wiki.openstreetmap.org/wiki/Overpass_turbo/Polygon_Features
-
     // NOTE: any properties that begin with `__` will not be displayed
 
     if let serde_json::Value::Object(mut obj) = el
@@ -66,7 +82,7 @@ fn element_to_feature(
                 obj.insert("__children_ids".to_string(), way.nodes.clone().into());
             }
             Element::Node(node) => {
-                if let Some(way_id) = way_map.get(&node.id) {
+                if let Some(way_id) = maps.way_map.get(&node.id) {
                     obj.insert("__way_id".to_string(), (*way_id).into());
                 }
             }
@@ -79,20 +95,23 @@ fn element_to_feature(
     Some(feat)
 }
 
-fn element_to_geometry(el: &Element, node_map: &BTreeMap<u64, Vec<f64>>) -> Option<Geometry> {
+fn element_to_geometry(el: &Element, maps: &Maps) -> Option<Geometry> {
     Some(
         match el {
             Element::Node(node) => Value::Point(vec![node.lon, node.lat]),
-            Element::Way(way) => Value::LineString(
-                way.nodes
-                    .iter()
-                    .filter_map(|id| node_map.get(id).cloned())
-                    .collect(),
-            ),
+            Element::Way(way) => {
+                let coords = node_coords(&way.nodes, maps.node_map);
+                if is_closed(&way.nodes) && is_polygon_feature(way.tags.as_ref()) {
+                    Value::Polygon(vec![coords])
+                } else {
+                    Value::LineString(coords)
+                }
+            }
+            Element::Relation(rel) if is_multipolygon(rel) => multipolygon(rel, maps)?,
             Element::Relation(rel) => Value::GeometryCollection(
                 rel.members
                     .iter()
-                    .flat_map(|el| element_to_geometry(el, node_map))
+                    .flat_map(|member| member_to_geometry(member, maps))
                     .collect(),
             ),
             Element::Area(_) => return None,
@@ -101,6 +120,238 @@ fn element_to_geometry(el: &Element, node_map: &BTreeMap<u64, Vec<f64>>) -> Opti
     )
 }
 
+fn member_to_geometry(member: &Member, maps: &Maps) -> Option<Geometry> {
+    Some(
+        match member.ty.as_str() {
+            "node" => Value::Point(maps.node_map.get(&member.id)?.clone()),
+            "way" => Value::LineString(node_coords(maps.way_nodes.get(&member.id)?, maps.node_map)),
+            // we don't resolve nested relations
+            _ => return None,
+        }
+        .into(),
+    )
+}
+
+fn node_coords(nodes: &[u64], node_map: &BTreeMap<u64, Vec<f64>>) -> Vec<Vec<f64>> {
+    nodes
+        .iter()
+        .filter_map(|id| node_map.get(id).cloned())
+        .collect()
+}
+
+/// a way is closed if it starts and ends on the same node
+fn is_closed(nodes: &[u64]) -> bool {
+    nodes.len() >= 4 && nodes.first() == nodes.last()
+}
+
+fn is_multipolygon(rel: &Relation) -> bool {
+    let ty = rel
+        .tags
+        .as_ref()
+        .and_then(|tags| tags.get("type"))
+        .and_then(|ty| ty.as_str());
+    matches!(ty, Some("multipolygon") | Some("boundary"))
+}
+
+/// Assembles the outer and inner ways of a multipolygon relation into a MultiPolygon
+///
+/// Returns `None` if none of the outer rings could be closed
+fn multipolygon(rel: &Relation, maps: &Maps) -> Option<Value> {
+    let ways_with_role = |role: &str| {
+        rel.members
+            .iter()
+            .filter(|m| m.ty == "way" && m.role == role)
+            .filter_map(|m| maps.way_nodes.get(&m.id))
+            .map(|nodes| nodes.to_vec())
+            .collect::<Vec<_>>()
+    };
+
+    let to_coords = |rings: Vec<Vec<u64>>| {
+        rings
+            .into_iter()
+            .map(|ring| node_coords(&ring, maps.node_map))
+            // rings with missing nodes can't be closed
+            .filter(|ring| ring.len() >= 4 && ring.first() == ring.last())
+            .collect::<Vec<_>>()
+    };
+
+    // an empty role is treated as outer, like overpass-turbo does
+    let mut outer_ways = ways_with_role("outer");
+    outer_ways.extend(ways_with_role(""));
+
+    let outers = to_coords(join_rings(outer_ways));
+    let inners = to_coords(join_rings(ways_with_role("inner")));
+
+    if outers.is_empty() {
+        return None;
+    }
+
+    let to_polygon = |ring: &[Vec<f64>]| {
+        let line = ring.iter().map(|p| (p[0], p[1])).collect::<Vec<_>>();
+        geo::Polygon::new(line.into(), vec![])
+    };
+    let outer_polygons = outers.iter().map(|r| to_polygon(r)).collect::<Vec<_>>();
+
+    let mut polygons = outers
+        .into_iter()
+        .map(|ring| vec![ring])
+        .collect::<Vec<_>>();
+
+    // put every inner ring in the outer ring that contains it.
+    // the whole ring is compared, since inner rings can touch the outer one
+    for inner in inners {
+        let inner_polygon = to_polygon(&inner);
+        match outer_polygons
+            .iter()
+            .position(|p| p.contains(&inner_polygon))
+        {
+            Some(i) => polygons[i].push(inner),
+            None => tracing::warn!(
+                "dropping inner ring of relation {} outside of its outer rings",
+                rel.id
+            ),
+        }
+    }
+
+    Some(Value::MultiPolygon(polygons))
+}
+
+/// Joins ways that share endpoints into closed rings
+///
+/// Ways that can't be joined into a closed ring are dropped
+fn join_rings(mut ways: Vec<Vec<u64>>) -> Vec<Vec<u64>> {
+    ways.retain(|w| w.len() >= 2);
+
+    let mut rings = vec![];
+    while let Some(mut ring) = ways.pop() {
+        while ring.first() != ring.last() {
+            let first = ring[0];
+            let last = ring[ring.len() - 1];
+
+            let Some(i) = ways.iter().position(|w| {
+                w[0] == last || w[w.len() - 1] == last || w[0] == first || w[w.len() - 1] == first
+            }) else {
+                break;
+            };
+            let mut way = ways.swap_remove(i);
+
+            if way[0] == last {
+                ring.extend_from_slice(&way[1..]);
+            } else if way[way.len() - 1] == last {
+                way.reverse();
+                ring.extend_from_slice(&way[1..]);
+            } else if way[way.len() - 1] == first {
+                way.pop();
+                way.extend_from_slice(&ring);
+                ring = way;
+            } else {
+                way.reverse();
+                way.pop();
+                way.extend_from_slice(&ring);
+                ring = way;
+            }
+        }
+
+        if is_closed(&ring) {
+            rings.push(ring);
+        }
+    }
+
+    rings
+}
+
+/// Which values of a key make a closed way a polygon
+enum PolygonRule {
+    All,
+    Whitelist(&'static [&'static str]),
+    Blacklist(&'static [&'static str]),
+}
+
+// https://wiki.openstreetmap.org/wiki/Overpass_turbo/Polygon_Features
+const POLYGON_FEATURES: &[(&str, PolygonRule)] = &[
+    ("building", PolygonRule::All),
+    (
+        "highway",
+        PolygonRule::Whitelist(&["services", "rest_area", "escape", "elevator"]),
+    ),
+    (
+        "natural",
+        PolygonRule::Blacklist(&["coastline", "cliff", "ridge", "arete", "tree_row"]),
+    ),
+    ("landuse", PolygonRule::All),
+    (
+        "waterway",
+        PolygonRule::Whitelist(&["riverbank", "dock", "boatyard", "dam"]),
+    ),
+    ("amenity", PolygonRule::All),
+    ("leisure", PolygonRule::All),
+    (
+        "barrier",
+        PolygonRule::Whitelist(&[
+            "city_wall",
+            "ditch",
+            "hedge",
+            "retaining_wall",
+            "wall",
+            "spikes",
+        ]),
+    ),
+    (
+        "railway",
+        PolygonRule::Whitelist(&["station", "turntable", "roundhouse", "platform"]),
+    ),
+    ("area", PolygonRule::All),
+    ("boundary", PolygonRule::All),
+    (
+        "man_made",
+        PolygonRule::Blacklist(&["cutline", "embankment", "pipeline"]),
+    ),
+    (
+        "power",
+        PolygonRule::Whitelist(&["plant", "substation", "generator", "transformer"]),
+    ),
+    ("place", PolygonRule::All),
+    ("shop", PolygonRule::All),
+    ("aeroway", PolygonRule::Blacklist(&["taxiway"])),
+    ("tourism", PolygonRule::All),
+    ("historic", PolygonRule::All),
+    ("public_transport", PolygonRule::All),
+    ("office", PolygonRule::All),
+    ("building:part", PolygonRule::All),
+    ("military", PolygonRule::All),
+    ("ruins", PolygonRule::All),
+    ("area:highway", PolygonRule::All),
+    ("craft", PolygonRule::All),
+    ("golf", PolygonRule::All),
+    ("indoor", PolygonRule::All),
+];
+
+/// whether a closed way with these tags should be displayed as a polygon
+fn is_polygon_feature(tags: Option<&serde_json::Value>) -> bool {
+    let Some(serde_json::Value::Object(tags)) = tags else {
+        return false;
+    };
+
+    if tags.get("area").and_then(|a| a.as_str()) == Some("no") {
+        return false;
+    }
+
+    POLYGON_FEATURES.iter().any(|(key, rule)| {
+        let Some(value) = tags.get(*key).and_then(|v| v.as_str()) else {
+            return false;
+        };
+        if value == "no" {
+            return false;
+        }
+
+        match rule {
+            PolygonRule::All => true,
+            PolygonRule::Whitelist(values) => values.contains(&value),
+            PolygonRule::Blacklist(values) => !values.contains(&value),
+        }
+    })
+}
+
 /// The intro stuff we don't care about, besides elements.
 #[derive(Debug, PartialEq, Clone, Deserialize)]
 pub struct Osm {
@@ -132,15 +383,25 @@ pub struct Way {
     pub tags: Option<serde_json::Value>,
 }
 
-/// A way is an ordered list of nodes.
+/// A relation is an ordered list of members, each with a role.
 ///
-/// [OpenStreetMap wiki](https://wiki.openstreetmap.org/wiki/Way)
+/// [OpenStreetMap wiki](https://wiki.openstreetmap.org/wiki/Relation)
 #[derive(Debug, PartialEq, Clone, Deserialize)]
 pub struct Relation {
     pub id: u64,
     pub tags: Option<serde_json::Value>,
 
-    pub members: Vec<Element>,
+    pub members: Vec<Member>,
+}
+
+/// A reference to an element that is part of a relation
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct Member {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(rename = "ref")]
+    pub id: u64,
+    pub role: String,
 }
 
 #[derive(Debug, PartialEq, Clone, Deserialize)]
@@ -189,5 +450,147 @@ impl Element {
     }
 }
 
-// TODO add tests for osm_to_geojson
-// but idk it works well so far and i dont plan on changing it any time soon so im leaving this for later
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn osm(elements: serde_json::Value) -> Osm {
+        serde_json::from_value(serde_json::json!({
+            "version": 0.6,
+            "generator": "test",
+            "osm3s": {},
+            "elements": elements,
+        }))
+        .unwrap()
+    }
+
+    fn square_nodes() -> serde_json::Value {
+        serde_json::json!([
+            { "type": "node", "id": 1, "lat": 0.0, "lon": 0.0 },
+            { "type": "node", "id": 2, "lat": 0.0, "lon": 1.0 },
+            { "type": "node", "id": 3, "lat": 1.0, "lon": 1.0 },
+            { "type": "node", "id": 4, "lat": 1.0, "lon": 0.0 },
+        ])
+    }
+
+    fn geometry_of(collection: &FeatureCollection, id: u64) -> &Value {
+        &collection
+            .features
+            .iter()
+            .find(|f| f.id == Some(feature::Id::Number(id.into())))
+            .unwrap()
+            .geometry
+            .as_ref()
+            .unwrap()
+            .value
+    }
+
+    #[test]
+    fn test_closed_building_is_polygon() {
+        let mut elements = square_nodes();
+        elements.as_array_mut().unwrap().push(serde_json::json!({
+            "type": "way", "id": 10, "nodes": [1, 2, 3, 4, 1], "tags": { "building": "yes" }
+        }));
+
+        let collection = osm_to_geojson(osm(elements));
+
+        assert!(
+            matches!(geometry_of(&collection, 10), Value::Polygon(rings) if rings[0].len() == 5)
+        );
+    }
+
+    #[test]
+    fn test_closed_highway_is_line() {
+        let mut elements = square_nodes();
+        elements.as_array_mut().unwrap().extend([
+            serde_json::json!({
+                "type": "way", "id": 10, "nodes": [1, 2, 3, 4, 1], "tags": { "highway": "residential" }
+            }),
+            serde_json::json!({
+                "type": "way", "id": 11, "nodes": [1, 2, 3, 4, 1], "tags": { "building": "yes", "area": "no" }
+            }),
+        ]);
+
+        let collection = osm_to_geojson(osm(elements));
+
+        assert!(matches!(geometry_of(&collection, 10), Value::LineString(_)));
+        assert!(matches!(geometry_of(&collection, 11), Value::LineString(_)));
+    }
+
+    #[test]
+    fn test_multipolygon_joins_ways() {
+        let mut elements = serde_json::json!([
+            { "type": "node", "id": 1, "lat": 0.0, "lon": 0.0 },
+            { "type": "node", "id": 2, "lat": 0.0, "lon": 10.0 },
+            { "type": "node", "id": 3, "lat": 10.0, "lon": 10.0 },
+            { "type": "node", "id": 4, "lat": 10.0, "lon": 0.0 },
+            { "type": "node", "id": 5, "lat": 4.0, "lon": 4.0 },
+            { "type": "node", "id": 6, "lat": 4.0, "lon": 6.0 },
+            { "type": "node", "id": 7, "lat": 6.0, "lon": 6.0 },
+            { "type": "way", "id": 10, "nodes": [1, 2, 3] },
+            // reversed, so it has to be flipped before joining
+            { "type": "way", "id": 11, "nodes": [1, 4, 3] },
+            { "type": "way", "id": 12, "nodes": [5, 6, 7, 5] },
+        ]);
+        elements.as_array_mut().unwrap().push(serde_json::json!({
+            "type": "relation",
+            "id": 20,
+            "members": [
+                { "type": "way", "ref": 10, "role": "outer" },
+                { "type": "way", "ref": 11, "role": "outer" },
+                { "type": "way", "ref": 12, "role": "inner" },
+            ],
+            "tags": { "type": "multipolygon", "landuse": "forest" }
+        }));
+
+        let collection = osm_to_geojson(osm(elements));
+
+        let Value::MultiPolygon(polygons) = geometry_of(&collection, 20) else {
+            panic!("relation is not a multipolygon");
+        };
+        assert_eq!(polygons.len(), 1);
+        // outer ring and inner ring
+        assert_eq!(polygons[0].len(), 2);
+        assert_eq!(polygons[0][0].len(), 5);
+        assert_eq!(polygons[0][0].first(), polygons[0][0].last());
+        assert_eq!(polygons[0][1].len(), 4);
+    }
+
+    #[test]
+    fn test_multipolygon_inner_touching_outer() {
+        let mut elements = serde_json::json!([
+            { "type": "node", "id": 1, "lat": 0.0, "lon": 0.0 },
+            { "type": "node", "id": 2, "lat": 0.0, "lon": 10.0 },
+            { "type": "node", "id": 3, "lat": 10.0, "lon": 10.0 },
+            { "type": "node", "id": 4, "lat": 10.0, "lon": 0.0 },
+            { "type": "node", "id": 5, "lat": 4.0, "lon": 4.0 },
+            { "type": "node", "id": 6, "lat": 4.0, "lon": 6.0 },
+            { "type": "node", "id": 7, "lat": 20.0, "lon": 20.0 },
+            { "type": "node", "id": 8, "lat": 20.0, "lon": 22.0 },
+            { "type": "way", "id": 10, "nodes": [1, 2, 3, 4, 1] },
+            // starts at a corner of the outer ring
+            { "type": "way", "id": 11, "nodes": [1, 5, 6, 1] },
+            // not inside any outer ring
+            { "type": "way", "id": 12, "nodes": [7, 8, 6, 7] },
+        ]);
+        elements.as_array_mut().unwrap().push(serde_json::json!({
+            "type": "relation",
+            "id": 20,
+            "members": [
+                { "type": "way", "ref": 10, "role": "outer" },
+                { "type": "way", "ref": 11, "role": "inner" },
+                { "type": "way", "ref": 12, "role": "inner" },
+            ],
+            "tags": { "type": "multipolygon", "landuse": "forest" }
+        }));
+
+        let collection = osm_to_geojson(osm(elements));
+
+        let Value::MultiPolygon(polygons) = geometry_of(&collection, 20) else {
+            panic!("relation is not a multipolygon");
+        };
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].len(), 2);
+        assert_eq!(polygons[0][1][0], vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use thiserror::Error;
+use tokio::time::sleep;
+
+use crate::osm_to_geojson::Osm;
+
+const DEFAULT_ENDPOINT: &str = "https://overpass-api.de/api/interpreter";
+/// Times to retry an endpoint that is rate limiting us or timing out before moving to the next one
+const MAX_RETRIES: u32 = 2;
+/// Milliseconds to wait before the first retry. Doubles on each retry
+const RETRY_DELAY: u64 = 1000;
+/// Seconds to wait on top of the query timeout before considering the endpoint unresponsive
+const TIMEOUT_MARGIN: u64 = 10;
+
+#[derive(Error, Debug)]
+pub enum OverpassError {
+    #[error("Overpass: {0}")]
+    Syntax(String),
+    #[error("All Overpass endpoints failed")]
+    Unavailable,
+    #[error("network error")]
+    Network(#[from] reqwest::Error),
+}
+
+/// Client for the Overpass API, which fails over to the next endpoint when one is unavailable
+pub struct OverpassClient {
+    client: reqwest::Client,
+    endpoints: Vec<String>,
+}
+
+impl OverpassClient {
+    pub fn new(endpoints: Vec<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent("Underpass, underpass.versary.town, annie@versary.town")
+            .build()
+            .expect("failed to build reqwest client");
+
+        let endpoints = if endpoints.is_empty() {
+            vec![DEFAULT_ENDPOINT.to_string()]
+        } else {
+            endpoints
+        };
+
+        Self { client, endpoints }
+    }
+
+    /// Reads the endpoints from `OVERPASS_ENDPOINTS`, as a comma separated list
+    pub fn from_env() -> Self {
+        let endpoints = std::env::var("OVERPASS_ENDPOINTS")
+            .map(|endpoints| {
+                endpoints
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|e| !e.is_empty())
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self::new(endpoints)
+    }
+
+    /// Runs `query` on the first endpoint that answers it
+    ///
+    /// Returns the response and the endpoint that was used
+    pub async fn query(&self, query: &str, timeout: u32) -> Result<(Osm, String), OverpassError> {
+        let timeout = Duration::from_secs(timeout as u64 + TIMEOUT_MARGIN);
+
+        for endpoint in &self.endpoints {
+            let mut retries = 0;
+            loop {
+                let res = self
+                    .client
+                    .post(endpoint)
+                    .timeout(timeout)
+                    .body(query.to_string())
+                    .send()
+                    .await;
+
+                let res = match res {
+                    Ok(res) => res,
+                    Err(err) => {
+                        tracing::warn!("request to {endpoint} failed: {err}");
+                        break;
+                    }
+                };
+
+                match res.status() {
+                    StatusCode::OK => match res.json().await {
+                        Ok(osm) => return Ok((osm, endpoint.clone())),
+                        // truncated results, or an error page from a proxy in front of the mirror
+                        Err(err) => {
+                            tracing::warn!(
+                                "{endpoint} returned invalid results, trying next endpoint: {err}"
+                            );
+                            break;
+                        }
+                    },
+                    StatusCode::TOO_MANY_REQUESTS | StatusCode::GATEWAY_TIMEOUT
+                        if retries < MAX_RETRIES =>
+                    {
+                        tracing::debug!("{endpoint} returned {}, retrying", res.status());
+                        sleep(Duration::from_millis(RETRY_DELAY * 2u64.pow(retries))).await;
+                        retries += 1;
+                    }
+                    StatusCode::BAD_REQUEST => {
+                        return Err(OverpassError::Syntax(res.text().await?));
+                    }
+                    status => {
+                        tracing::warn!("{endpoint} returned {status}, trying next endpoint");
+                        break;
+                    }
+                }
+            }
+        }
+
+        Err(OverpassError::Unavailable)
+    }
+}
+
+impl Default for OverpassClient {
+    fn default() -> Self {
+        Self::new(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// serves `body` with a 200 status on a local port, returning its url
+    async fn mirror(body: &'static str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = axum::Router::new().route("/", axum::routing::post(move || async move { body }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_invalid_results() {
+        let broken = mirror("<html>502 Bad Gateway</html>").await;
+        let working =
+            mirror(r#"{"version": 0.6, "generator": "test", "osm3s": {}, "elements": []}"#).await;
+
+        let client = OverpassClient::new(vec![broken, working.clone()]);
+        let (osm, endpoint) = client.query("node(1);out;", 1).await.unwrap();
+        assert_eq!(endpoint, working);
+        assert!(osm.elements.is_empty());
+    }
+}
@@ -1,10 +1,13 @@
-use crate::{app_state::AppState, search, taginfo::taginfo_path};
+use crate::{
+    app_state::AppState, cache::CacheStatsReport, elevation_api, export, search,
+    taginfo::taginfo_path,
+};
 
 use std::sync::Arc;
 
 use axum::{
     extract::State,
-    response::Html,
+    response::{Html, Json},
     routing::{get, post},
     Router,
 };
@@ -17,6 +20,13 @@ pub fn make_router() -> Router<Arc<AppState>> {
         .route("/index.js", get(js))
         .route("/taginfo.json", get(get_taginfo))
         .route("/search", post(search::search))
+        .route("/search/stream", post(search::search_stream))
+        .route("/export", post(export::export_results))
+        .route("/cache/stats", get(cache_stats))
+        .route(
+            "/elevation",
+            get(elevation_api::get_elevation).post(elevation_api::post_elevation),
+        )
 }
 
 async fn home() -> Html<String> {
@@ -65,3 +75,7 @@ async fn get_taginfo(State(state): State<Arc<AppState>>) -> String {
         "[]".to_string()
     }
 }
+
+async fn cache_stats(State(state): State<Arc<AppState>>) -> Json<CacheStatsReport> {
+    Json(state.caches.stats().await)
+}
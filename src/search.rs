@@ -3,40 +3,90 @@ use std::{collections::HashMap, hash::Hash, sync::Arc};
 use axum::{
     extract::State,
     http::StatusCode,
-    response::{IntoResponse, Json},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json,
+    },
 };
-use geojson::GeoJson;
+use futures_util::Stream;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use thiserror::Error;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
 
 use crate::{
     app_state::AppState,
-    graph::{errors::GraphError, process::process_graph, Graph},
+    graph::{
+        errors::GraphError,
+        process::{process_graph, Layer, NodeEvent},
+        Graph,
+    },
 };
 
 pub async fn search(
     State(state): State<Arc<AppState>>,
     Json(json): Json<SearchParams>,
 ) -> Result<Json<SearchResults>, SearchError> {
+    Ok(Json(run_search(&state, json, None).await?))
+}
+
+/// Same as [`search`], but streams the progress of every node as Server-Sent Events
+///
+/// Sends a `node` event for every [`NodeEvent`], and finishes with either a `result` or an `error` event,
+/// containing the same json that [`search`] would return
+pub async fn search_stream(
+    State(state): State<Arc<AppState>>,
+    Json(json): Json<SearchParams>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let (tx, rx) = unbounded_channel();
+
+    let search = tokio::spawn(async move { run_search(&state, json, Some(tx)).await });
+
+    // the channel closes once the search finishes, so the result always comes after every node event
+    let events = UnboundedReceiverStream::new(rx)
+        .map(|event| Event::default().event("node").json_data(event));
+
+    let result = futures_util::stream::once(async move {
+        match search.await {
+            Ok(Ok(results)) => Event::default().event("result").json_data(results),
+            Ok(Err(err)) => Event::default().event("error").json_data(err.to_json()),
+            Err(err) => {
+                tracing::error!("search task failed: {err}");
+                Event::default()
+                    .event("error")
+                    .json_data(json!({ "error": "search failed", "data": { "format": "text" } }))
+            }
+        }
+    });
+
+    Sse::new(events.chain(result)).keep_alive(KeepAlive::default())
+}
+
+async fn run_search(
+    state: &AppState,
+    params: SearchParams,
+    events: Option<UnboundedSender<NodeEvent>>,
+) -> Result<SearchResults, SearchError> {
     let result = process_graph(
-        json.graph,
-        json.bbox,
-        &state.elevation_map,
+        params.graph,
+        params.bbox,
+        state.elevation_map.clone(),
         state.caches.clone(),
+        &state.overpass,
+        events,
     )
     .await?;
 
-    let geojson = GeoJson::FeatureCollection(result.collection);
-
-    Ok(Json(SearchResults {
-        data: geojson,
+    Ok(SearchResults {
+        layers: result.layers,
         processed_queries: result.processed_queries,
         geocode_areas: result.geocode_areas,
-    }))
+        endpoints: result.endpoints,
+    })
 }
 
-#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
 pub struct Bbox {
     pub ne: [f32; 2],
     pub sw: [f32; 2],
@@ -64,13 +114,16 @@ pub struct SearchParams {
 
 #[derive(Serialize)]
 pub struct SearchResults {
-    pub data: geojson::GeoJson,
+    /// One named FeatureCollection for every Map node
+    pub layers: Vec<Layer>,
     /// Node Id -> Processed query
     pub processed_queries: HashMap<String, String>,
     pub geocode_areas: Vec<GeocodeaArea>,
+    /// Node Id -> Overpass endpoint used
+    pub endpoints: HashMap<String, String>,
 }
 
-#[derive(Serialize, Default, Clone, Debug)]
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
 pub struct GeocodeaArea {
     pub id: u64,
     pub ty: String,
@@ -91,13 +144,20 @@ impl SearchError {
         match self {
             Self::Graph(GraphError::OqlSyntax { node_id, .. }) => Some(node_id),
             Self::Graph(GraphError::InputMissing { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::OutputMissing { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::InViewOf { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::Slope { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::TagFilter { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::Spatial { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::Cluster { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::Transform { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::Area { node_id, .. }) => Some(node_id),
+            Self::Graph(GraphError::Junction { node_id, .. }) => Some(node_id),
             _ => None,
         }
     }
-}
 
-impl IntoResponse for SearchError {
-    fn into_response(self) -> axum::response::Response {
+    fn to_json(&self) -> serde_json::Value {
         let data = if let Self::Graph(GraphError::OqlSyntax { query, error, .. }) = &self {
             json!({
                 "format": "xml",
@@ -110,12 +170,16 @@ impl IntoResponse for SearchError {
             })
         };
 
-        let json = json!({
+        json!({
             "error": format!("{self}"),
             "data": data,
             "node_id": self.node_id(),
-        });
+        })
+    }
+}
 
-        (StatusCode::INTERNAL_SERVER_ERROR, Json(json)).into_response()
+impl IntoResponse for SearchError {
+    fn into_response(self) -> axum::response::Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(self.to_json())).into_response()
     }
 }